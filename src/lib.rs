@@ -22,7 +22,7 @@ mod utils;
 
 use crate::cli::{Cli, Command, Configuration, HttpCommand, StdioCommand};
 use crate::protocol::http::{HttpProtocol, HttpServerConfig};
-use crate::servers::elasticsearch;
+use crate::servers::{elasticsearch, federation};
 use crate::utils::interpolator;
 use rmcp::transport::stdio;
 use rmcp::transport::streamable_http_server::session::never::NeverSessionManager;
@@ -122,6 +122,12 @@ pub async fn setup_services(config: &Option<PathBuf>, container_mode: bool) -> a
         Err(err) => return Err(err)?,
     };
 
-    let handler = elasticsearch::ElasticsearchMcp::new_with_config(config.elasticsearch, container_mode)?;
+    let mode = config.elasticsearch.mode;
+    let incl_excl = config.elasticsearch.tools.incl_excl.clone();
+    let mut handler = elasticsearch::ElasticsearchMcp::new_with_config(config.elasticsearch, container_mode)?;
+
+    // Tools from downstream MCP servers, filtered like the builtin tools
+    handler.add_tools(federation::tool_router(config.mcp_servers, mode, incl_excl.as_ref()).await);
+
     Ok(handler)
 }
@@ -0,0 +1,221 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Async ES|QL queries, for queries that take longer than client timeouts.
+//!
+//! The query waits for completion for a limited time. If it's still running, its id is returned
+//! and its results can be collected later.
+
+use crate::servers::elasticsearch::base_tools::{
+    EsBaseTools, EsqlQueryParams, EsqlQueryRequest, EsqlQueryResponse, warning_headers,
+};
+use crate::servers::elasticsearch::{EsqlResultFormat, handle_error, internal_error};
+use elasticsearch::esql::EsqlAsyncQueryGetParts;
+use elasticsearch::http::response::Response;
+use rmcp::RoleServer;
+use rmcp::handler::server::tool::Parameters;
+use rmcp::model::{CallToolResult, Content};
+use rmcp::service::RequestContext;
+use rmcp_macros::{tool, tool_router};
+use serde::{Deserialize, Serialize};
+
+/// How long the query waits for completion, if not specified
+const DEFAULT_WAIT_SECS: u64 = 30;
+
+/// How long the query and its results are kept, if not specified
+const DEFAULT_KEEP_ALIVE: &str = "1h";
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct EsqlAsyncParams {
+    #[serde(flatten)]
+    query: EsqlQueryParams,
+
+    /// Number of seconds to wait for the query to complete before returning its id (optional)
+    wait_secs: Option<u64>,
+
+    /// How long the query and its results are kept, e.g. `1h` (optional)
+    keep_alive: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct EsqlAsyncGetParams {
+    /// Id of the async ES|QL query
+    id: String,
+
+    /// Number of seconds to wait for the query to complete (optional)
+    wait_secs: Option<u64>,
+
+    /// Output format of the results (optional, defaults to `json`)
+    format: Option<EsqlResultFormat>,
+}
+
+#[tool_router(router = async_esql_tool_router, vis = "pub(super)")]
+impl EsBaseTools {
+    //---------------------------------------------------------------------------------------------
+    /// Tool: async ES|QL
+    #[tool(
+        description = "Perform a long-running Elasticsearch ES|QL query, e.g. a cross-cluster query. Waits for the query to complete, or returns an id to get its results later with esql_async_get.",
+        annotations(title = "Elasticsearch async ES|QL query", read_only_hint = true)
+    )]
+    async fn esql_async(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(EsqlAsyncParams {
+            query,
+            wait_secs,
+            keep_alive,
+        }): Parameters<EsqlAsyncParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let format = query.format.unwrap_or_default();
+        let request = EsqlAsyncQueryRequest {
+            request: query.into_request(),
+            wait_for_completion_timeout: format!("{}s", wait_secs.unwrap_or(DEFAULT_WAIT_SECS)),
+            keep_alive: keep_alive.unwrap_or(DEFAULT_KEEP_ALIVE.to_string()),
+            keep_on_completion: false,
+        };
+
+        let response = es_client.esql().async_query().body(request).send().await;
+
+        Ok(CallToolResult::success(read_async_response(response, format).await?))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: get async ES|QL results
+    #[tool(
+        description = "Get the status or the results of an async ES|QL query.",
+        annotations(title = "Get Elasticsearch async ES|QL query", read_only_hint = true)
+    )]
+    async fn esql_async_get(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(EsqlAsyncGetParams { id, wait_secs, format }): Parameters<EsqlAsyncGetParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let timeout = format!("{}s", wait_secs.unwrap_or(DEFAULT_WAIT_SECS));
+        let response = es_client
+            .esql()
+            .async_query_get(EsqlAsyncQueryGetParts::Id(&id))
+            .wait_for_completion_timeout(&timeout)
+            .send()
+            .await;
+
+        let format = format.unwrap_or_default();
+        Ok(CallToolResult::success(read_async_response(response, format).await?))
+    }
+}
+
+async fn read_async_response(
+    response: Result<Response, elasticsearch::Error>,
+    format: EsqlResultFormat,
+) -> Result<Vec<Content>, rmcp::Error> {
+    let response = handle_error(response).await?;
+    let warnings = warning_headers(&response);
+    let response: EsqlAsyncQueryResponse = response.json().await.map_err(internal_error)?;
+
+    response.into_contents(warnings, format)
+}
+
+//-------------------------------------------------------------------------------------------------
+// Type definitions for ES requests and responses
+
+#[derive(Serialize, Deserialize)]
+pub struct EsqlAsyncQueryRequest {
+    #[serde(flatten)]
+    pub request: EsqlQueryRequest,
+    pub wait_for_completion_timeout: String,
+    pub keep_alive: String,
+    pub keep_on_completion: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct EsqlAsyncQueryResponse {
+    /// Missing if the query completed before the timeout
+    pub id: Option<String>,
+    #[serde(default)]
+    pub is_running: bool,
+    #[serde(flatten)]
+    pub response: EsqlQueryResponse,
+}
+
+impl EsqlAsyncQueryResponse {
+    fn into_contents(self, warnings: Vec<String>, format: EsqlResultFormat) -> Result<Vec<Content>, rmcp::Error> {
+        if self.is_running {
+            let id = self.id.unwrap_or_default();
+            return Ok(vec![Content::text(format!(
+                "ES|QL query {id} is still running. Use esql_async_get to get its results."
+            ))]);
+        }
+
+        let mut contents = warnings
+            .into_iter()
+            .chain(self.response.partial_warnings())
+            .map(|w| Content::text(format!("Warning: {w}")))
+            .collect::<Vec<_>>();
+
+        contents.push(Content::text("Results"));
+        contents.extend(self.response.into_contents(format)?);
+
+        Ok(contents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_results() -> anyhow::Result<()> {
+        let response: EsqlAsyncQueryResponse = serde_json::from_str(
+            r#"{
+                "id": "FmNJRUZ1YWZCU3dHY1BIOUhaenVSRkEaaXFlZ3h4c1RTWFNocDdnY2FSaERnUTozNDE=",
+                "is_running": false,
+                "is_partial": true,
+                "columns": [{ "name": "count", "type": "long" }],
+                "values": [[42]],
+                "_clusters": {
+                    "details": {
+                        "(local)": { "status": "successful" },
+                        "remote1": { "status": "partial", "failures": [
+                            { "reason": { "type": "task_cancelled_exception", "reason": "cancelled" } }
+                        ]}
+                    }
+                }
+            }"#,
+        )?;
+
+        assert_eq!(
+            response.response.partial_warnings(),
+            vec![
+                "Results are partial".to_string(),
+                "Cluster 'remote1' is partial: cancelled".to_string()
+            ]
+        );
+        assert_eq!(
+            response
+                .into_contents(vec!["evaluation failed".to_string()], EsqlResultFormat::Json)?
+                .len(),
+            5
+        );
+
+        let response: EsqlAsyncQueryResponse = serde_json::from_str(r#"{ "id": "abc", "is_running": true }"#)?;
+        assert_eq!(response.into_contents(Vec::new(), EsqlResultFormat::Csv)?.len(), 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,292 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Async search, for long-running searches.
+//!
+//! The search is submitted with `_async_search` and polled until it completes, sending progress
+//! notifications with the number of shards searched. If it doesn't complete in time, its id is
+//! returned so that the client can get its results later. A search that completes while polling
+//! is deleted once its results are returned, rather than kept until it expires.
+
+use crate::servers::elasticsearch::base_tools::{EsBaseTools, SearchResult, add_source_fields};
+use crate::servers::elasticsearch::{ProgressNotifier, handle_error, read_json};
+use elasticsearch::Elasticsearch;
+use elasticsearch::async_search::{AsyncSearchDeleteParts, AsyncSearchGetParts, AsyncSearchSubmitParts};
+use rmcp::RoleServer;
+use rmcp::handler::server::tool::Parameters;
+use rmcp::model::{CallToolResult, Content};
+use rmcp::service::RequestContext;
+use rmcp_macros::{tool, tool_router};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::time::{Duration, Instant};
+
+/// How long a stored async search is kept, if not specified
+const DEFAULT_KEEP_ALIVE: &str = "1h";
+
+/// How long the tool waits for the search to complete, if not specified
+const DEFAULT_MAX_WAIT_SECS: u64 = 60;
+
+/// How long each request waits for completion, i.e. the interval between progress notifications
+const POLL_INTERVAL: &str = "2s";
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct AsyncSearchParams {
+    /// Name of the Elasticsearch index to search
+    index: String,
+
+    /// Name of the fields that need to be returned (optional)
+    fields: Option<Vec<String>>,
+
+    /// Complete Elasticsearch query DSL object that can include query, size, from, sort, aggs, etc.
+    query_body: Map<String, Value>,
+
+    /// Maximum number of seconds to wait for the search to complete before returning its id (optional)
+    max_wait_secs: Option<u64>,
+
+    /// How long the search results are kept, e.g. `1h` (optional)
+    keep_alive: Option<String>,
+
+    /// Return the metadata of each hit (`_id`, `_index`, `_score`, `highlight`, `fields`,
+    /// `inner_hits` and `sort`) alongside its `_source` (optional, defaults to false)
+    include_metadata: Option<bool>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct AsyncSearchIdParams {
+    /// Id of the async search
+    id: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct AsyncSearchGetParams {
+    /// Id of the async search
+    id: String,
+
+    /// Return the metadata of each hit alongside its `_source` (optional, defaults to false)
+    include_metadata: Option<bool>,
+}
+
+#[tool_router(router = async_search_tool_router, vis = "pub(super)")]
+impl EsBaseTools {
+    //---------------------------------------------------------------------------------------------
+    /// Tool: async search
+    #[tool(
+        description = "Perform a long-running Elasticsearch search (e.g. large aggregations) with the provided query DSL. Waits for the search to complete, or returns an id to get its results later with async_search_get.",
+        annotations(title = "Elasticsearch async search", read_only_hint = true)
+    )]
+    async fn async_search(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(AsyncSearchParams {
+            index,
+            fields,
+            query_body,
+            max_wait_secs,
+            keep_alive,
+            include_metadata,
+        }): Parameters<AsyncSearchParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let progress = ProgressNotifier::new(&req_ctx);
+        let es_client = self.es_client.get(req_ctx);
+
+        let mut query_body = query_body;
+        add_source_fields(&mut query_body, fields);
+
+        let deadline = Instant::now() + Duration::from_secs(max_wait_secs.unwrap_or(DEFAULT_MAX_WAIT_SECS));
+        let keep_alive = keep_alive.unwrap_or(DEFAULT_KEEP_ALIVE.to_string());
+
+        let async_search = es_client.async_search();
+        let response = async_search
+            .submit(AsyncSearchSubmitParts::Index(&[&index]))
+            .wait_for_completion_timeout(POLL_INTERVAL)
+            .keep_alive(&keep_alive)
+            .body(query_body)
+            .send()
+            .await;
+        let mut response: AsyncSearchResponse = read_json(response).await?;
+
+        // The id is missing if the search completed before the timeout and wasn't stored
+        while response.is_running
+            && let Some(id) = &response.id
+            && Instant::now() < deadline
+        {
+            if let Some((done, total)) = response.shards_progress() {
+                progress
+                    .notify(done, Some(total), format!("Searched {done} of {total} shards"))
+                    .await;
+            }
+
+            let next = async_search
+                .get(AsyncSearchGetParts::Id(id))
+                .wait_for_completion_timeout(POLL_INTERVAL)
+                .send()
+                .await;
+            response = read_json(next).await?;
+        }
+
+        // A search that completed while polling is stored, and no longer needed
+        let completed_id = response.id.clone().filter(|_| !response.is_running);
+        let contents = response.into_contents(include_metadata.unwrap_or_default())?;
+        if let Some(id) = completed_id {
+            delete_async_search(&es_client, &id).await;
+        }
+
+        Ok(CallToolResult::success(contents))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: get async search results
+    #[tool(
+        description = "Get the status or the results of an async search.",
+        annotations(title = "Get Elasticsearch async search", read_only_hint = true)
+    )]
+    async fn async_search_get(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(AsyncSearchGetParams { id, include_metadata }): Parameters<AsyncSearchGetParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let response = es_client
+            .async_search()
+            .get(AsyncSearchGetParts::Id(&id))
+            .wait_for_completion_timeout(POLL_INTERVAL)
+            .send()
+            .await;
+        let response: AsyncSearchResponse = read_json(response).await?;
+
+        Ok(CallToolResult::success(
+            response.into_contents(include_metadata.unwrap_or_default())?,
+        ))
+    }
+}
+
+// Deleting an async search changes the state of the cluster, and can delete searches submitted by
+// other clients: it's only available in read-write mode.
+#[tool_router(router = async_search_write_tool_router, vis = "pub(super)")]
+impl EsBaseTools {
+    //---------------------------------------------------------------------------------------------
+    /// Tool: delete async search
+    #[tool(
+        description = "Cancel an async search if it's still running, and delete its results.",
+        annotations(
+            title = "Delete Elasticsearch async search",
+            read_only_hint = false,
+            destructive_hint = true
+        )
+    )]
+    async fn async_search_delete(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(AsyncSearchIdParams { id }): Parameters<AsyncSearchIdParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let response = es_client
+            .async_search()
+            .delete(AsyncSearchDeleteParts::Id(&id))
+            .send()
+            .await;
+        let _: Value = read_json(response).await?;
+
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "Async search {id} deleted"
+        ))]))
+    }
+}
+
+/// Delete an async search whose results were returned. Not an error for the client if it fails:
+/// the search will expire anyway.
+async fn delete_async_search(es_client: &Elasticsearch, id: &str) {
+    let response = es_client
+        .async_search()
+        .delete(AsyncSearchDeleteParts::Id(id))
+        .send()
+        .await;
+
+    if let Err(err) = handle_error(response).await {
+        tracing::warn!("Failed to delete async search: {err}");
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Type definitions for ES responses
+
+#[derive(Serialize, Deserialize)]
+pub struct AsyncSearchResponse {
+    pub id: Option<String>,
+    pub is_running: bool,
+    pub is_partial: bool,
+    pub response: SearchResult,
+}
+
+impl AsyncSearchResponse {
+    /// Number of shards that have been searched, and total number of shards
+    fn shards_progress(&self) -> Option<(u32, u32)> {
+        let shards = self.response.shards.as_ref()?;
+        Some((shards.successful + shards.skipped + shards.failed, shards.total))
+    }
+
+    fn into_contents(self, include_metadata: bool) -> Result<Vec<Content>, rmcp::Error> {
+        let mut contents = Vec::new();
+
+        if self.is_running {
+            let progress = self.shards_progress();
+            let id = self.id.unwrap_or_default();
+            let mut status = format!("Async search {id} is still running");
+            if let Some((done, total)) = progress {
+                status.push_str(&format!(" ({done} of {total} shards searched)"));
+            }
+            status.push_str(". Use async_search_get to get its results.");
+            contents.push(Content::text(status));
+            return Ok(contents);
+        }
+
+        if self.is_partial {
+            let failed = self.response.shards.as_ref().map(|s| s.failed).unwrap_or_default();
+            contents.push(Content::text(format!("Results are partial: {failed} shards failed")));
+        }
+        contents.extend(self.response.into_contents(include_metadata)?);
+
+        Ok(contents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn running_search_progress() -> anyhow::Result<()> {
+        let response: AsyncSearchResponse = serde_json::from_str(
+            r#"{
+                "id": "FmRldE8zREVEUzA2ZVpUeGs2ejJFUFEaMkZ5QTVrSTZSaVN3WlNFVmtlWHJsdzoxMDc=",
+                "is_partial": true,
+                "is_running": true,
+                "response": {
+                    "_shards": { "total": 562, "successful": 3, "skipped": 0, "failed": 0 },
+                    "hits": { "total": { "value": 157483, "relation": "gte" }, "hits": [] }
+                }
+            }"#,
+        )?;
+
+        assert_eq!(response.shards_progress(), Some((3, 562)));
+        assert_eq!(response.into_contents(false)?.len(), 1);
+        Ok(())
+    }
+}
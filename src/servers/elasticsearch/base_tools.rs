@@ -15,36 +15,83 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use crate::servers::elasticsearch::{EsClientProvider, read_json};
+use crate::servers::IncludeExclude;
+use crate::servers::elasticsearch::errors::EsError;
+use crate::servers::elasticsearch::{
+    CustomPrompt, EsClientProvider, EsqlResultFormat, Mode, ResponseLimits, paginated_search, prompts, read_json,
+    resources,
+};
 use elasticsearch::cat::{CatIndicesParts, CatShardsParts};
+use elasticsearch::http::response::Response;
 use elasticsearch::indices::IndicesGetMappingParts;
 use elasticsearch::{Elasticsearch, SearchParts};
 use indexmap::IndexMap;
-use rmcp::handler::server::tool::{Parameters, ToolRouter};
+use indexmap::map::Entry;
+use rmcp::handler::server::tool::{Parameters, ToolCallContext, ToolRouter};
 use rmcp::model::{
-    CallToolResult, Content, Implementation, JsonObject, ProtocolVersion, ServerCapabilities, ServerInfo,
+    CallToolRequestParam, CallToolResult, Content, GetPromptRequestParam, GetPromptResult, Implementation, JsonObject,
+    ListPromptsResult, ListResourceTemplatesResult, ListResourcesResult, ListToolsResult, PaginatedRequestParam,
+    PromptsCapability, ProtocolVersion, ReadResourceRequestParam, ReadResourceResult, ServerCapabilities, ServerInfo,
 };
 use rmcp::service::RequestContext;
 use rmcp::{RoleServer, ServerHandler};
-use rmcp_macros::{tool, tool_handler, tool_router};
+use rmcp_macros::{tool, tool_router};
 use serde::{Deserialize, Serialize};
 use serde_aux::prelude::*;
 use serde_json::{Map, Value, json};
 use std::collections::HashMap;
+use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct EsBaseTools {
-    es_client: EsClientProvider,
+    pub(super) es_client: EsClientProvider,
     tool_router: ToolRouter<EsBaseTools>,
+    pub(super) response_limits: ResponseLimits,
+    prompts: Arc<IndexMap<String, CustomPrompt>>,
 }
 
 impl EsBaseTools {
-    pub fn new(es_client: Elasticsearch) -> Self {
+    /// Create the base tools, filtered by `incl_excl`, and add `custom_tools`. A custom tool with
+    /// the same name as a builtin tool replaces it.
+    ///
+    /// Write tools are only added in read-write `mode`. Results of all tools are truncated to fit
+    /// in `response_limits`.
+    pub fn new(
+        es_client: EsClientProvider,
+        mode: Mode,
+        response_limits: ResponseLimits,
+        incl_excl: Option<&IncludeExclude>,
+        custom_tools: ToolRouter<Self>,
+        prompts: IndexMap<String, CustomPrompt>,
+    ) -> Self {
+        let mut tool_router = Self::tool_router()
+            + Self::paginated_search_tool_router()
+            + Self::async_search_tool_router()
+            + Self::async_esql_tool_router()
+            + Self::document_tool_router()
+            + Self::cluster_tool_router()
+            + Self::validation_tool_router()
+            + Self::field_tool_router();
+        if mode == Mode::ReadWrite {
+            tool_router.merge(Self::write_tool_router());
+            tool_router.merge(Self::async_search_write_tool_router());
+        }
+        if let Some(incl_excl) = incl_excl {
+            incl_excl.filter_router(&mut tool_router);
+        }
+
         Self {
-            es_client: EsClientProvider::new(es_client),
-            tool_router: Self::tool_router(),
+            es_client,
+            tool_router: tool_router + custom_tools,
+            response_limits,
+            prompts: Arc::new(prompts),
         }
     }
+
+    /// Add tools, replacing existing tools with the same name.
+    pub fn add_tools(&mut self, tools: ToolRouter<Self>) {
+        self.tool_router.merge(tools);
+    }
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
@@ -69,12 +116,59 @@ struct SearchParams {
 
     /// Complete Elasticsearch query DSL object that can include query, size, from, sort, etc.
     query_body: Map<String, Value>, // note: just Value doesn't work, as Claude would send a string
+
+    /// Return the metadata of each hit (`_id`, `_index`, `_score`, `highlight`, `fields`,
+    /// `inner_hits` and `sort`) alongside its `_source` (optional, defaults to false)
+    include_metadata: Option<bool>,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
-struct EsqlQueryParams {
+pub struct EsqlQueryParams {
     /// Complete Elasticsearch ES|QL query
     query: String,
+
+    /// Query parameters, to use instead of inlining values in the query (optional). Either an
+    /// array of values for positional parameters (`?1`, `?2` or `?`), or an object for named
+    /// parameters (`?name`).
+    params: Option<EsqlParams>,
+
+    /// Query DSL object used to filter documents before the ES|QL query runs (optional)
+    filter: Option<Map<String, Value>>,
+
+    /// Locale used to format dates and numbers, e.g. `fr-FR` (optional)
+    locale: Option<String>,
+
+    /// Time zone used for date functions and date formatting, e.g. `Europe/Paris` (optional)
+    time_zone: Option<String>,
+
+    /// Output format of the results (optional, defaults to `json`). Tabular formats like `csv`
+    /// or `markdown` are more compact for large results.
+    pub format: Option<EsqlResultFormat>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+#[serde(untagged)]
+pub enum EsqlParams {
+    Positional(Vec<Value>),
+    Named(Map<String, Value>),
+}
+
+impl EsqlQueryParams {
+    pub fn into_request(self) -> EsqlQueryRequest {
+        let params = match self.params {
+            None => Vec::new(),
+            Some(EsqlParams::Positional(values)) => values,
+            Some(EsqlParams::Named(values)) => values.into_iter().map(|(name, value)| json!({ name: value })).collect(),
+        };
+
+        EsqlQueryRequest {
+            query: self.query,
+            params,
+            filter: self.filter.map(Value::Object),
+            locale: self.locale,
+            time_zone: self.time_zone,
+        }
+    }
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
@@ -97,15 +191,7 @@ impl EsBaseTools {
         Parameters(ListIndicesParams { index_pattern }): Parameters<ListIndicesParams>,
     ) -> Result<CallToolResult, rmcp::Error> {
         let es_client = self.es_client.get(req_ctx);
-        let response = es_client
-            .cat()
-            .indices(CatIndicesParts::Index(&[&index_pattern]))
-            .h(&["index", "status", "docs.count"])
-            .format("json")
-            .send()
-            .await;
-
-        let response: Vec<CatIndexResponse> = read_json(response).await?;
+        let response = cat_indices(&es_client, &index_pattern).await?;
 
         Ok(CallToolResult::success(vec![
             Content::text(format!("Found {} indices:", response.len())),
@@ -116,7 +202,7 @@ impl EsBaseTools {
     //---------------------------------------------------------------------------------------------
     /// Tool: get mappings for an index
     #[tool(
-        description = "Get field mappings for a specific Elasticsearch index",
+        description = "Get field mappings for an Elasticsearch index or index pattern, as a flat list of fields with their dotted path, type, multi-fields and main settings. Mappings of all matching indices are merged, and fields whose type differ across indices are reported",
         annotations(title = "Get ES index mappings", read_only_hint = true)
     )]
     async fn get_mappings(
@@ -133,13 +219,28 @@ impl EsBaseTools {
 
         let response: MappingResponse = read_json(response).await?;
 
-        // use the first mapping (we can have many if the name is a wildcard)
-        let mapping = response.values().next().unwrap();
+        // We can have many indices if the name is a wildcard or a data stream
+        let merged = merge_mappings(response);
 
-        Ok(CallToolResult::success(vec![
-            Content::text(format!("Mappings for index {index}:")),
-            Content::json(mapping)?,
-        ]))
+        if merged.indices.is_empty() {
+            return Ok(CallToolResult::success(vec![Content::text(format!(
+                "No index matches '{index}'"
+            ))]));
+        }
+
+        let mut contents = vec![Content::text(format!(
+            "Mappings for {} indices matching '{index}':",
+            merged.indices.len()
+        ))];
+        if !merged.conflicts.is_empty() {
+            contents.push(Content::text(format!(
+                "{} fields have different types across indices, see 'conflicts'",
+                merged.conflicts.len()
+            )));
+        }
+        contents.push(Content::json(merged)?);
+
+        Ok(CallToolResult::success(contents))
     }
 
     //---------------------------------------------------------------------------------------------
@@ -159,22 +260,13 @@ impl EsBaseTools {
             index,
             fields,
             query_body,
+            include_metadata,
         }): Parameters<SearchParams>,
     ) -> Result<CallToolResult, rmcp::Error> {
         let es_client = self.es_client.get(req_ctx);
 
         let mut query_body = query_body;
-
-        if let Some(fields) = fields {
-            // Augment _source if it exists
-            if let Some(Value::Array(values)) = query_body.get_mut("_source") {
-                for field in fields.into_iter() {
-                    values.push(Value::String(field))
-                }
-            } else {
-                query_body.insert("_source".to_string(), json!(fields));
-            }
-        }
+        add_source_fields(&mut query_body, fields);
 
         let response = es_client
             .search(SearchParts::Index(&[&index]))
@@ -184,38 +276,9 @@ impl EsBaseTools {
 
         let response: SearchResult = read_json(response).await?;
 
-        let mut results: Vec<Content> = Vec::new();
-
-        // Send result stats only if it's not pure aggregation results
-        if response.aggregations.is_empty() || !response.hits.hits.is_empty() {
-            let total = response
-                .hits
-                .total
-                .map(|t| t.value.to_string())
-                .unwrap_or("unknown".to_string());
-
-            results.push(Content::text(format!(
-                "Total results: {}, showing {}.",
-                total,
-                response.hits.hits.len()
-            )));
-        }
-
-        // Original prototype sent a separate content for each document, it seems to confuse some LLMs
-        // for hit in &response.hits.hits {
-        //     results.push(Content::json(&hit.source)?);
-        // }
-        if !response.hits.hits.is_empty() {
-            let sources = response.hits.hits.iter().map(|hit| &hit.source).collect::<Vec<_>>();
-            results.push(Content::json(&sources)?);
-        }
-
-        if !response.aggregations.is_empty() {
-            results.push(Content::text("Aggregations results:"));
-            results.push(Content::json(&response.aggregations)?);
-        }
-
-        Ok(CallToolResult::success(results))
+        Ok(CallToolResult::success(
+            response.into_contents(include_metadata.unwrap_or_default())?,
+        ))
     }
 
     //---------------------------------------------------------------------------------------------
@@ -227,29 +290,18 @@ impl EsBaseTools {
     async fn esql(
         &self,
         req_ctx: RequestContext<RoleServer>,
-        Parameters(EsqlQueryParams { query }): Parameters<EsqlQueryParams>,
+        Parameters(params): Parameters<EsqlQueryParams>,
     ) -> Result<CallToolResult, rmcp::Error> {
         let es_client = self.es_client.get(req_ctx);
 
-        let request = EsqlQueryRequest { query };
-
-        let response = es_client.esql().query().body(request).send().await;
+        let format = params.format.unwrap_or_default();
+        let response = es_client.esql().query().body(params.into_request()).send().await;
         let response: EsqlQueryResponse = read_json(response).await?;
 
-        // Transform response into an array of objects
-        let mut objects: Vec<Value> = Vec::new();
-        for row in response.values.into_iter() {
-            let mut obj = Map::new();
-            for (i, value) in row.into_iter().enumerate() {
-                obj.insert(response.columns[i].name.clone(), value);
-            }
-            objects.push(Value::Object(obj));
-        }
+        let mut contents = vec![Content::text("Results")];
+        contents.extend(response.into_contents(format)?);
 
-        Ok(CallToolResult::success(vec![
-            Content::text("Results"),
-            Content::json(objects)?,
-        ]))
+        Ok(CallToolResult::success(contents))
     }
 
     //---------------------------------------------------------------------------------------------
@@ -290,16 +342,132 @@ impl EsBaseTools {
     }
 }
 
-#[tool_handler]
+/// Add `fields` to the `_source` of a search request
+pub fn add_source_fields(query_body: &mut Map<String, Value>, fields: Option<Vec<String>>) {
+    if let Some(fields) = fields {
+        // Augment _source if it exists
+        if let Some(Value::Array(values)) = query_body.get_mut("_source") {
+            for field in fields.into_iter() {
+                values.push(Value::String(field))
+            }
+        } else {
+            query_body.insert("_source".to_string(), json!(fields));
+        }
+    }
+}
+
+/// List indices matching a pattern
+pub async fn cat_indices(es_client: &Elasticsearch, index_pattern: &str) -> Result<Vec<CatIndexResponse>, rmcp::Error> {
+    let response = es_client
+        .cat()
+        .indices(CatIndicesParts::Index(&[index_pattern]))
+        .h(&["index", "status", "docs.count"])
+        .format("json")
+        .send()
+        .await;
+
+    read_json(response).await
+}
+
+// Tool methods are the same as those generated by `#[tool_handler]`, with Elasticsearch errors
+// converted to tool error results, and response limits applied
 impl ServerHandler for EsBaseTools {
     fn get_info(&self) -> ServerInfo {
+        let mut capabilities = ServerCapabilities::builder().enable_resources().enable_tools().build();
+        if !self.prompts.is_empty() {
+            capabilities.prompts = Some(PromptsCapability::default());
+        }
+
         ServerInfo {
             protocol_version: ProtocolVersion::V_2025_03_26,
-            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            capabilities,
             server_info: Implementation::from_build_env(),
             instructions: Some("Provides access to Elasticsearch".to_string()),
         }
     }
+
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(context.clone());
+        let fitted = paginated_search::FITTED_TOOLS.contains(&request.name.as_ref());
+        let tcc = ToolCallContext::new(self, request, context);
+
+        let result = match self.tool_router.call(tcc).await {
+            Ok(result) => result,
+            Err(err) => match EsError::from_rmcp(&err) {
+                Some(mut es_error) => {
+                    es_error.add_index_suggestions(&es_client).await;
+                    es_error.into_result()?
+                }
+                None => return Err(err),
+            },
+        };
+        if fitted {
+            return Ok(result);
+        }
+        Ok(self.response_limits.apply(result))
+    }
+
+    async fn list_tools(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, rmcp::Error> {
+        Ok(ListToolsResult::with_all_items(self.tool_router.list_all()))
+    }
+
+    async fn list_prompts(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListPromptsResult, rmcp::Error> {
+        Ok(ListPromptsResult::with_all_items(prompts::list(&self.prompts)))
+    }
+
+    async fn get_prompt(
+        &self,
+        request: GetPromptRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<GetPromptResult, rmcp::Error> {
+        let Some(prompt) = self.prompts.get(&request.name) else {
+            return Err(rmcp::Error::invalid_params(
+                format!("prompt '{}' not found", request.name),
+                None,
+            ));
+        };
+
+        let es_client = self.es_client.get(context);
+        prompts::get(&es_client, prompt, request.arguments).await
+    }
+
+    async fn list_resources(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ListResourcesResult, rmcp::Error> {
+        let es_client = self.es_client.get(context);
+        Ok(ListResourcesResult::with_all_items(resources::list(&es_client).await?))
+    }
+
+    async fn list_resource_templates(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourceTemplatesResult, rmcp::Error> {
+        Ok(ListResourceTemplatesResult::with_all_items(resources::templates()))
+    }
+
+    async fn read_resource(
+        &self,
+        request: ReadResourceRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ReadResourceResult, rmcp::Error> {
+        let es_client = self.es_client.get(context);
+        resources::read(&es_client, request.uri).await
+    }
 }
 
 //-------------------------------------------------------------------------------------------------
@@ -307,28 +475,122 @@ impl ServerHandler for EsBaseTools {
 
 //----- Search request
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct SearchResult {
+    /// Point in time id, for searches using a PIT
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pit_id: Option<String>,
+    #[serde(rename = "_shards", default, skip_serializing_if = "Option::is_none")]
+    pub shards: Option<ShardStats>,
     pub hits: Hits,
     #[serde(default)]
     pub aggregations: IndexMap<String, Value>,
 }
 
-#[derive(Serialize, Deserialize)]
+impl SearchResult {
+    /// Convert the search response into tool result contents. Hits are returned with their
+    /// metadata if `include_metadata` is true, and as documents otherwise.
+    pub fn into_contents(self, include_metadata: bool) -> Result<Vec<Content>, rmcp::Error> {
+        let mut results: Vec<Content> = Vec::new();
+
+        // Send result stats only if it's not pure aggregation results
+        if self.aggregations.is_empty() || !self.hits.hits.is_empty() {
+            let total = self
+                .hits
+                .total
+                .map(|t| t.value.to_string())
+                .unwrap_or("unknown".to_string());
+
+            results.push(Content::text(format!(
+                "Total results: {}, showing {}.",
+                total,
+                self.hits.hits.len()
+            )));
+        }
+
+        // Original prototype sent a separate content for each document, it seems to confuse some LLMs
+        // for hit in &self.hits.hits {
+        //     results.push(Content::json(&hit.source)?);
+        // }
+        if include_metadata && !self.hits.hits.is_empty() {
+            results.push(Content::json(&self.hits.hits)?);
+        } else if !self.hits.hits.is_empty() {
+            let documents = self.hits.hits.iter().map(Hit::document).collect::<Vec<_>>();
+            results.push(Content::json(&documents)?);
+        }
+
+        if !self.aggregations.is_empty() {
+            results.push(Content::text("Aggregations results:"));
+            results.push(Content::json(&self.aggregations)?);
+        }
+
+        Ok(results)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ShardStats {
+    pub total: u32,
+    pub successful: u32,
+    #[serde(default)]
+    pub skipped: u32,
+    #[serde(default)]
+    pub failed: u32,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Hits {
     pub total: Option<TotalHits>,
     pub hits: Vec<Hit>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct TotalHits {
     pub value: u64,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Hit {
-    #[serde(rename = "_source")]
-    pub source: Value,
+    #[serde(rename = "_id", default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(rename = "_index", default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<String>,
+    /// Null if the search is sorted on other fields than the score
+    #[serde(rename = "_score", default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
+    /// Missing if `_source` is disabled in the request or in the mappings
+    #[serde(rename = "_source", default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub highlight: Option<Value>,
+    /// Values requested with the `fields`, `docvalue_fields` or `script_fields` search properties
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fields: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inner_hits: Option<Value>,
+    /// Sort values, used to paginate with `search_after`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sort: Option<Vec<Value>>,
+}
+
+impl Hit {
+    /// The document's `_source`, or its `fields` values if `_source` is disabled
+    pub fn document(&self) -> &Value {
+        self.source.as_ref().or(self.fields.as_ref()).unwrap_or(&Value::Null)
+    }
+}
+
+//----- Search template request
+
+#[derive(Serialize, Deserialize)]
+pub struct SearchTemplateRequest {
+    /// Stored template id
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// Inline template
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<Value>,
+    pub params: JsonObject,
 }
 
 //----- Cat responses
@@ -367,22 +629,203 @@ pub struct Mappings {
 pub struct Mapping {
     #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
     pub meta: Option<JsonObject>,
-    properties: HashMap<String, MappingProperty>,
+    #[serde(default)]
+    properties: IndexMap<String, MappingProperty>,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct MappingProperty {
+    /// Field type. Missing for object fields that only have `properties`.
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub type_: Option<String>,
+    /// Sub-fields of `object` and `nested` fields
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub properties: IndexMap<String, MappingProperty>,
+    /// Multi-fields
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub fields: IndexMap<String, MappingProperty>,
+    #[serde(flatten)]
+    pub settings: HashMap<String, serde_json::Value>,
+}
+
+/// Field settings that are kept in flattened mappings, as they're useful to write queries.
+const FLAT_FIELD_SETTINGS: &[&str] = &[
+    "analyzer",
+    "search_analyzer",
+    "normalizer",
+    "format",
+    "dims",
+    "element_type",
+    "similarity",
+    "index",
+    "doc_values",
+    "enabled",
+    "ignore_above",
+    "scaling_factor",
+    "inference_id",
+];
+
+/// A mapping flattened to a list of fields with their dotted path
+#[derive(Serialize, Deserialize)]
+pub struct FlatMapping {
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<JsonObject>,
+    pub fields: Vec<FlatField>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct FlatField {
+    pub path: String,
     #[serde(rename = "type")]
     pub type_: String,
+    /// Target of `alias` fields. Mappings call it `path`, which is the field's own path here.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alias_path: Option<String>,
+    /// Multi-fields, as dotted path and type
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub multi_fields: IndexMap<String, String>,
     #[serde(flatten)]
-    pub settings: HashMap<String, serde_json::Value>,
+    pub settings: IndexMap<String, Value>,
 }
 
-//----- ES|QL
+impl Mapping {
+    pub fn flatten(&self) -> FlatMapping {
+        let mut fields = Vec::new();
+        flatten_properties("", &self.properties, &mut fields);
+        FlatMapping {
+            meta: self.meta.clone(),
+            fields,
+        }
+    }
+}
+
+/// Mappings of several indices, merged in a single list of fields
+#[derive(Serialize, Deserialize)]
+pub struct MergedMappings {
+    /// Indices whose mappings were merged
+    pub indices: Vec<String>,
+    /// `_meta` of the indices that have one
+    #[serde(rename = "_meta", default, skip_serializing_if = "IndexMap::is_empty")]
+    pub meta: IndexMap<String, JsonObject>,
+    pub fields: Vec<FlatField>,
+    /// Fields whose type differ across indices
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conflicts: Vec<FieldConflict>,
+}
 
 #[derive(Serialize, Deserialize)]
+pub struct FieldConflict {
+    pub path: String,
+    /// Indices having each of the field's types
+    pub types: IndexMap<String, Vec<String>>,
+}
+
+/// Merge the mappings of all indices of a get mappings response.
+///
+/// Indices are processed in name order, and the first definition of a field is kept, with the
+/// multi-fields of all indices.
+pub fn merge_mappings(response: MappingResponse) -> MergedMappings {
+    let mut indices = response.into_iter().collect::<Vec<_>>();
+    indices.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut meta = IndexMap::new();
+    let mut fields = IndexMap::<String, FlatField>::new();
+    let mut field_types = IndexMap::<String, IndexMap<String, Vec<String>>>::new();
+
+    for (index, mappings) in &indices {
+        let flat = mappings.mappings.flatten();
+        if let Some(index_meta) = flat.meta {
+            meta.insert(index.clone(), index_meta);
+        }
+
+        for field in flat.fields {
+            field_types
+                .entry(field.path.clone())
+                .or_default()
+                .entry(field.type_.clone())
+                .or_default()
+                .push(index.clone());
+
+            match fields.entry(field.path.clone()) {
+                Entry::Occupied(mut entry) => {
+                    for (path, type_) in field.multi_fields {
+                        entry.get_mut().multi_fields.entry(path).or_insert(type_);
+                    }
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(field);
+                }
+            }
+        }
+    }
+
+    let conflicts = field_types
+        .into_iter()
+        .filter(|(_, types)| types.len() > 1)
+        .map(|(path, types)| FieldConflict { path, types })
+        .collect();
+
+    MergedMappings {
+        indices: indices.into_iter().map(|(index, _)| index).collect(),
+        meta,
+        fields: fields.into_values().collect(),
+        conflicts,
+    }
+}
+
+fn flatten_properties(prefix: &str, properties: &IndexMap<String, MappingProperty>, result: &mut Vec<FlatField>) {
+    for (name, prop) in properties {
+        let path = format!("{prefix}{name}");
+
+        // Object fields are only represented by their sub-fields, unless they have settings
+        // like `"enabled": false`. Nested fields are kept, as they require nested queries.
+        let type_ = prop.type_.as_deref().unwrap_or("object");
+        if type_ != "object" || prop.properties.is_empty() {
+            let settings = FLAT_FIELD_SETTINGS
+                .iter()
+                .filter_map(|key| prop.settings.get(*key).map(|v| (key.to_string(), v.clone())))
+                .collect();
+
+            let multi_fields = prop
+                .fields
+                .iter()
+                .map(|(sub_name, sub_prop)| {
+                    let sub_type = sub_prop.type_.clone().unwrap_or("object".to_string());
+                    (format!("{path}.{sub_name}"), sub_type)
+                })
+                .collect();
+
+            let alias_path = match prop.settings.get("path") {
+                Some(Value::String(target)) if type_ == "alias" => Some(target.clone()),
+                _ => None,
+            };
+
+            result.push(FlatField {
+                path: path.clone(),
+                type_: type_.to_string(),
+                alias_path,
+                multi_fields,
+                settings,
+            });
+        }
+
+        flatten_properties(&format!("{path}."), &prop.properties, result);
+    }
+}
+
+//----- ES|QL
+
+#[derive(Serialize, Deserialize, Default)]
 pub struct EsqlQueryRequest {
     pub query: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub params: Vec<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_zone: Option<String>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -395,6 +838,239 @@ pub struct Column {
 #[derive(Serialize, Deserialize)]
 pub struct EsqlQueryResponse {
     pub is_partial: Option<bool>,
+    /// Missing in async query responses while the query is running
+    #[serde(default)]
     pub columns: Vec<Column>,
+    #[serde(default)]
     pub values: Vec<Vec<Value>>,
+    /// Status of remote clusters, for cross-cluster queries
+    #[serde(rename = "_clusters", default, skip_serializing_if = "Option::is_none")]
+    pub clusters: Option<EsqlClusters>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct EsqlClusters {
+    #[serde(default)]
+    pub details: IndexMap<String, EsqlClusterDetails>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct EsqlClusterDetails {
+    pub status: String,
+    #[serde(default)]
+    pub failures: Vec<Value>,
+}
+
+impl EsqlQueryResponse {
+    /// Warnings about partial results, with the clusters that didn't return all their results
+    pub fn partial_warnings(&self) -> Vec<String> {
+        if self.is_partial != Some(true) {
+            return Vec::new();
+        }
+
+        let mut warnings = vec!["Results are partial".to_string()];
+        let details = self.clusters.iter().flat_map(|c| c.details.iter());
+        for (name, cluster) in details.filter(|(_, c)| c.status != "successful") {
+            let mut warning = format!("Cluster '{name}' is {}", cluster.status);
+            let reasons = cluster
+                .failures
+                .iter()
+                .map(|f| f.pointer("/reason/reason").and_then(Value::as_str).map(str::to_string))
+                .map(|r| r.unwrap_or("unknown reason".to_string()))
+                .collect::<Vec<_>>();
+            if !reasons.is_empty() {
+                warning.push_str(&format!(": {}", reasons.join(", ")));
+            }
+            warnings.push(warning);
+        }
+        warnings
+    }
+
+    /// Transform the response rows into an array of objects
+    pub fn into_objects(self) -> Vec<Value> {
+        let mut objects: Vec<Value> = Vec::new();
+        for row in self.values.into_iter() {
+            let mut obj = Map::new();
+            for (i, value) in row.into_iter().enumerate() {
+                obj.insert(self.columns[i].name.clone(), value);
+            }
+            objects.push(Value::Object(obj));
+        }
+        objects
+    }
+}
+
+/// Messages of the `Warning` headers of a response. ES|QL uses them to report issues like values
+/// that couldn't be evaluated.
+pub fn warning_headers(response: &Response) -> Vec<String> {
+    response
+        .headers()
+        .get_all("warning")
+        .iter()
+        .filter_map(|h| h.to_str().ok())
+        .map(|h| {
+            // Format is `299 Elasticsearch-<version> "<message>"`
+            match (h.find('"'), h.rfind('"')) {
+                (Some(start), Some(end)) if start < end => h[start + 1..end].to_string(),
+                _ => h.to_string(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flatten_mappings() -> anyhow::Result<()> {
+        let mapping: Mapping = serde_json::from_str(
+            r#"{
+                "properties": {
+                    "@timestamp": { "type": "date", "format": "strict_date_optional_time" },
+                    "host": {
+                        "properties": {
+                            "name": {
+                                "type": "text",
+                                "analyzer": "standard",
+                                "fields": { "keyword": { "type": "keyword", "ignore_above": 256 } }
+                            }
+                        }
+                    },
+                    "events": {
+                        "type": "nested",
+                        "properties": { "id": { "type": "long" } }
+                    },
+                    "raw": { "type": "object", "enabled": false },
+                    "embedding": { "type": "dense_vector", "dims": 384, "index": true, "store": false },
+                    "hostname": { "type": "alias", "path": "host.name" }
+                }
+            }"#,
+        )?;
+
+        let flat = serde_json::to_value(mapping.flatten())?;
+        assert_eq!(
+            flat,
+            json!({
+                "fields": [
+                    { "path": "@timestamp", "type": "date", "format": "strict_date_optional_time" },
+                    {
+                        "path": "host.name",
+                        "type": "text",
+                        "analyzer": "standard",
+                        "multi_fields": { "host.name.keyword": "keyword" }
+                    },
+                    { "path": "events", "type": "nested" },
+                    { "path": "events.id", "type": "long" },
+                    { "path": "raw", "type": "object", "enabled": false },
+                    { "path": "embedding", "type": "dense_vector", "dims": 384, "index": true },
+                    { "path": "hostname", "type": "alias", "alias_path": "host.name" }
+                ]
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn esql_request_params() -> anyhow::Result<()> {
+        let params: EsqlQueryParams = serde_json::from_value(json!({
+            "query": "FROM logs | WHERE status == ?1 | LIMIT ?2",
+            "params": ["error", 10],
+            "filter": { "range": { "@timestamp": { "gte": "now-1d" } } },
+            "time_zone": "Europe/Paris"
+        }))?;
+        assert_eq!(
+            serde_json::to_value(params.into_request())?,
+            json!({
+                "query": "FROM logs | WHERE status == ?1 | LIMIT ?2",
+                "params": ["error", 10],
+                "filter": { "range": { "@timestamp": { "gte": "now-1d" } } },
+                "time_zone": "Europe/Paris"
+            })
+        );
+
+        let params: EsqlQueryParams = serde_json::from_str(
+            r#"{ "query": "FROM logs | WHERE status == ?status | LIMIT ?limit", "params": { "status": "error", "limit": 10 } }"#,
+        )?;
+        // Named parameters can be in any order
+        let request = params.into_request();
+        assert_eq!(request.params.len(), 2);
+        assert!(request.params.contains(&json!({ "status": "error" })));
+        assert!(request.params.contains(&json!({ "limit": 10 })));
+        Ok(())
+    }
+
+    #[test]
+    fn merge_index_mappings() -> anyhow::Result<()> {
+        let response: MappingResponse = serde_json::from_str(
+            r#"{
+                "logs-2": { "mappings": { "properties": {
+                    "status": { "type": "keyword" },
+                    "message": { "type": "text", "fields": { "raw": { "type": "keyword" } } }
+                }}},
+                "logs-1": { "mappings": { "properties": {
+                    "status": { "type": "long" },
+                    "message": { "type": "text" }
+                }}}
+            }"#,
+        )?;
+
+        let merged = serde_json::to_value(merge_mappings(response))?;
+        assert_eq!(
+            merged,
+            json!({
+                "indices": ["logs-1", "logs-2"],
+                "fields": [
+                    { "path": "status", "type": "long" },
+                    { "path": "message", "type": "text", "multi_fields": { "message.raw": "keyword" } }
+                ],
+                "conflicts": [
+                    { "path": "status", "types": { "long": ["logs-1"], "keyword": ["logs-2"] } }
+                ]
+            })
+        );
+
+        let merged = merge_mappings(MappingResponse::new());
+        assert!(merged.indices.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn search_hit_metadata() -> anyhow::Result<()> {
+        let response = json!({
+            "hits": {
+                "total": { "value": 2 },
+                "hits": [
+                    {
+                        "_index": "logs", "_id": "1", "_score": 1.5,
+                        "_source": { "message": "disk full" },
+                        "highlight": { "message": ["<em>disk</em> full"] }
+                    },
+                    { "_index": "logs", "_id": "2", "_score": null, "fields": { "message": ["disk ok"] } }
+                ]
+            }
+        });
+
+        let result: SearchResult = serde_json::from_value(response.clone())?;
+        let contents = result.into_contents(false)?;
+        assert_eq!(
+            serde_json::from_str::<Value>(&contents[1].as_text().unwrap().text)?,
+            json!([{ "message": "disk full" }, { "message": ["disk ok"] }])
+        );
+
+        let result: SearchResult = serde_json::from_value(response)?;
+        let contents = result.into_contents(true)?;
+        assert_eq!(
+            serde_json::from_str::<Value>(&contents[1].as_text().unwrap().text)?,
+            json!([
+                {
+                    "_index": "logs", "_id": "1", "_score": 1.5,
+                    "_source": { "message": "disk full" },
+                    "highlight": { "message": ["<em>disk</em> full"] }
+                },
+                { "_index": "logs", "_id": "2", "fields": { "message": ["disk ok"] } }
+            ])
+        );
+        Ok(())
+    }
 }
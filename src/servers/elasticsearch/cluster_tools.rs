@@ -0,0 +1,578 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Tools that report on the state of the cluster: health, nodes, disk and shard allocation.
+
+use crate::servers::elasticsearch::base_tools::EsBaseTools;
+use crate::servers::elasticsearch::errors::EsError;
+use crate::servers::elasticsearch::{internal_error, read_json};
+use elasticsearch::cat::CatAllocationParts;
+use elasticsearch::cluster::ClusterHealthParts;
+use elasticsearch::http::StatusCode;
+use elasticsearch::params::Level;
+use indexmap::IndexMap;
+use rmcp::RoleServer;
+use rmcp::handler::server::tool::Parameters;
+use rmcp::model::{CallToolResult, Content};
+use rmcp::service::RequestContext;
+use rmcp_macros::{tool, tool_router};
+use serde::{Deserialize, Serialize};
+use serde_aux::prelude::*;
+use serde_json::json;
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct ClusterHealthParams {
+    /// Optional index name or pattern. If present, the health of each matching index is also returned.
+    index: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct AllocationExplainParams {
+    /// Index of the shard to explain. If missing, the first unassigned shard is explained.
+    index: Option<String>,
+
+    /// Shard number (optional, defaults to 0)
+    shard: Option<u32>,
+
+    /// Explain the primary shard rather than a replica (optional, defaults to true)
+    primary: Option<bool>,
+}
+
+#[tool_router(router = cluster_tool_router, vis = "pub(super)")]
+impl EsBaseTools {
+    //---------------------------------------------------------------------------------------------
+    /// Tool: cluster health
+    #[tool(
+        description = "Get the cluster health status (green, yellow, red) and shard counts, for the cluster or specific indices.",
+        annotations(title = "Get ES cluster health", read_only_hint = true)
+    )]
+    async fn cluster_health(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(ClusterHealthParams { index }): Parameters<ClusterHealthParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let indices: [&str; 1];
+        let (parts, level) = match &index {
+            Some(index) => {
+                indices = [index];
+                (ClusterHealthParts::Index(&indices), Level::Indices)
+            }
+            None => (ClusterHealthParts::None, Level::Cluster),
+        };
+        let response = es_client.cluster().health(parts).level(level).send().await;
+
+        let response: ClusterHealthResponse = read_json(response).await?;
+
+        Ok(CallToolResult::success(vec![
+            Content::text(format!("Cluster {} is {}", response.cluster_name, response.status)),
+            Content::json(response)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: list nodes
+    #[tool(
+        description = "List the cluster nodes with their roles, heap, RAM, CPU, load and disk usage.",
+        annotations(title = "List ES nodes", read_only_hint = true)
+    )]
+    async fn list_nodes(&self, req_ctx: RequestContext<RoleServer>) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let response = es_client
+            .cat()
+            .nodes()
+            .format("json")
+            .h(&[
+                "name",
+                "node.role",
+                "master",
+                "heap.percent",
+                "heap.current",
+                "heap.max",
+                "ram.percent",
+                "cpu",
+                "load_1m",
+                "disk.used_percent",
+                "disk.used",
+                "disk.total",
+            ])
+            .send()
+            .await;
+
+        let response: Vec<CatNodesResponse> = read_json(response).await?;
+
+        Ok(CallToolResult::success(vec![
+            Content::text(format!("Found {} nodes:", response.len())),
+            Content::json(response)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: disk allocation
+    #[tool(
+        description = "Get the number of shards and the disk usage of each data node.",
+        annotations(title = "Get ES disk allocation", read_only_hint = true)
+    )]
+    async fn get_allocation(&self, req_ctx: RequestContext<RoleServer>) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let response = es_client
+            .cat()
+            .allocation(CatAllocationParts::None)
+            .format("json")
+            .h(&[
+                "node",
+                "shards",
+                "disk.indices",
+                "disk.used",
+                "disk.avail",
+                "disk.total",
+                "disk.percent",
+            ])
+            .send()
+            .await;
+
+        let response: Vec<CatAllocationResponse> = read_json(response).await?;
+
+        Ok(CallToolResult::success(vec![
+            Content::text(format!("Allocation of {} nodes:", response.len())),
+            Content::json(response)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: shard allocation explain
+    #[tool(
+        description = "Explain why a shard is unassigned or can't be moved, with a summary of the allocation deciders for each node. Explains the first unassigned shard if no index is given.",
+        annotations(title = "Explain ES shard allocation", read_only_hint = true)
+    )]
+    async fn explain_allocation(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(AllocationExplainParams { index, shard, primary }): Parameters<AllocationExplainParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let cluster = es_client.cluster();
+        let request = cluster.allocation_explain();
+        let response = match &index {
+            Some(index) => {
+                let body = json!({
+                    "index": index,
+                    "shard": shard.unwrap_or(0),
+                    "primary": primary.unwrap_or(true),
+                });
+                request.body(body).send().await
+            }
+            None => match request.send().await {
+                // Without a shard, ES returns a 400 error if there are no unassigned shards
+                Ok(response) if response.status_code() == StatusCode::BAD_REQUEST => {
+                    let body = response.text().await.map_err(internal_error)?;
+                    let error = EsError::from_response(StatusCode::BAD_REQUEST, &body);
+                    if !is_no_unassigned_shards(&error) {
+                        return Err(error.into_rmcp());
+                    }
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        "There are no unassigned shards. Provide an index to explain the allocation of an assigned shard.",
+                    )]));
+                }
+                response => response,
+            },
+        };
+
+        let response: AllocationExplainResponse = read_json(response).await?;
+
+        Ok(CallToolResult::success(vec![Content::text(response.summary())]))
+    }
+}
+
+/// Is this the error returned when explaining the first unassigned shard and there's none? Older
+/// versions of ES use a different message.
+fn is_no_unassigned_shards(error: &EsError) -> bool {
+    error.type_ == "illegal_argument_exception"
+        && (error.reason.contains("unable to find any unassigned shards")
+            || error.reason.contains("there are no unassigned shards"))
+}
+
+//-------------------------------------------------------------------------------------------------
+// Type definitions for ES responses
+
+#[derive(Serialize, Deserialize)]
+pub struct ClusterHealthResponse {
+    pub cluster_name: String,
+    pub status: String,
+    pub timed_out: bool,
+    pub number_of_nodes: u64,
+    pub number_of_data_nodes: u64,
+    pub active_primary_shards: u64,
+    pub active_shards: u64,
+    pub relocating_shards: u64,
+    pub initializing_shards: u64,
+    pub unassigned_shards: u64,
+    pub delayed_unassigned_shards: u64,
+    pub number_of_pending_tasks: u64,
+    pub active_shards_percent_as_number: f64,
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub indices: IndexMap<String, IndexHealth>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct IndexHealth {
+    pub status: String,
+    pub number_of_shards: u64,
+    pub number_of_replicas: u64,
+    pub active_primary_shards: u64,
+    pub active_shards: u64,
+    pub relocating_shards: u64,
+    pub initializing_shards: u64,
+    pub unassigned_shards: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CatNodesResponse {
+    pub name: String,
+    #[serde(rename = "node.role")]
+    pub role: String,
+    /// `*` for the elected master node
+    pub master: String,
+    #[serde(rename = "heap.percent", deserialize_with = "deserialize_option_number_from_string")]
+    pub heap_percent: Option<u64>,
+    #[serde(rename = "heap.current")]
+    pub heap_current: Option<String>,
+    #[serde(rename = "heap.max")]
+    pub heap_max: Option<String>,
+    #[serde(rename = "ram.percent", deserialize_with = "deserialize_option_number_from_string")]
+    pub ram_percent: Option<u64>,
+    #[serde(deserialize_with = "deserialize_option_number_from_string")]
+    pub cpu: Option<u64>,
+    #[serde(deserialize_with = "deserialize_option_number_from_string")]
+    pub load_1m: Option<f64>,
+    #[serde(
+        rename = "disk.used_percent",
+        deserialize_with = "deserialize_option_number_from_string"
+    )]
+    pub disk_used_percent: Option<f64>,
+    #[serde(rename = "disk.used")]
+    pub disk_used: Option<String>,
+    #[serde(rename = "disk.total")]
+    pub disk_total: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CatAllocationResponse {
+    /// Node name, or `UNASSIGNED` for shards that aren't allocated
+    pub node: String,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub shards: u64,
+    #[serde(rename = "disk.indices")]
+    pub disk_indices: Option<String>,
+    #[serde(rename = "disk.used")]
+    pub disk_used: Option<String>,
+    #[serde(rename = "disk.avail")]
+    pub disk_avail: Option<String>,
+    #[serde(rename = "disk.total")]
+    pub disk_total: Option<String>,
+    #[serde(rename = "disk.percent", deserialize_with = "deserialize_option_number_from_string")]
+    pub disk_percent: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AllocationExplainResponse {
+    pub index: String,
+    pub shard: u32,
+    pub primary: bool,
+    pub current_state: String,
+    pub unassigned_info: Option<UnassignedInfo>,
+    pub current_node: Option<CurrentNode>,
+    pub can_allocate: Option<String>,
+    pub allocate_explanation: Option<String>,
+    pub can_remain_on_current_node: Option<String>,
+    #[serde(default)]
+    pub can_remain_decisions: Vec<Decider>,
+    pub can_rebalance_cluster: Option<String>,
+    pub rebalance_explanation: Option<String>,
+    #[serde(default)]
+    pub node_allocation_decisions: Vec<NodeAllocationDecision>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct UnassignedInfo {
+    pub reason: String,
+    pub at: String,
+    pub details: Option<String>,
+    pub last_allocation_status: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CurrentNode {
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct NodeAllocationDecision {
+    pub node_name: String,
+    pub node_decision: String,
+    #[serde(default)]
+    pub deciders: Vec<Decider>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Decider {
+    pub decider: String,
+    pub decision: String,
+    pub explanation: String,
+}
+
+/// Maximum number of node names listed for a decider in the allocation summary
+const MAX_LISTED_NODES: usize = 5;
+
+impl AllocationExplainResponse {
+    /// A readable summary of the allocation explanation. Node deciders with the same outcome are
+    /// grouped, as large clusters often have many nodes rejecting a shard for the same reason.
+    pub fn summary(&self) -> String {
+        let mut lines = Vec::new();
+
+        let copy = if self.primary { "primary" } else { "replica" };
+        let mut status = format!(
+            "Shard {} of index {} ({copy}) is {}",
+            self.shard, self.index, self.current_state
+        );
+        if let Some(node) = &self.current_node {
+            status.push_str(&format!(" on node {}", node.name));
+        }
+        lines.push(status);
+
+        if let Some(info) = &self.unassigned_info {
+            let mut reason = format!("Unassigned since {}, reason: {}", info.at, info.reason);
+            if let Some(details) = &info.details {
+                reason.push_str(&format!(" ({details})"));
+            }
+            lines.push(reason);
+        }
+
+        let decisions = [
+            ("Can allocate", &self.can_allocate, &self.allocate_explanation),
+            ("Can remain on current node", &self.can_remain_on_current_node, &None),
+            (
+                "Can rebalance",
+                &self.can_rebalance_cluster,
+                &self.rebalance_explanation,
+            ),
+        ];
+        for (label, decision, explanation) in decisions {
+            if let Some(decision) = decision {
+                match explanation {
+                    Some(explanation) => lines.push(format!("{label}: {decision}. {explanation}")),
+                    None => lines.push(format!("{label}: {decision}")),
+                }
+            }
+        }
+
+        if !self.can_remain_decisions.is_empty() {
+            lines.push("Deciders preventing the shard from remaining on its node:".to_string());
+            for decider in &self.can_remain_decisions {
+                lines.push(format!("- {}: {}", decider.decider, decider.explanation));
+            }
+        }
+
+        // Group nodes by decider outcome, ignoring positive decisions. ES only returns deciders
+        // that aren't YES, so a node without deciders may still not receive the shard, e.g. if
+        // its decision is `worse_balance` or `throttled`.
+        let mut groups = IndexMap::<(&str, &str, &str), Vec<&str>>::new();
+        let mut other_decisions = IndexMap::<&str, Vec<&str>>::new();
+        let mut possible_nodes = Vec::new();
+        for node in &self.node_allocation_decisions {
+            let blocking = node.deciders.iter().filter(|d| d.decision != "YES").collect::<Vec<_>>();
+            if node.node_decision == "yes" {
+                possible_nodes.push(node.node_name.as_str());
+            } else if blocking.is_empty() {
+                other_decisions
+                    .entry(&node.node_decision)
+                    .or_default()
+                    .push(&node.node_name);
+            }
+            for decider in blocking {
+                groups
+                    .entry((&decider.decider, &decider.decision, &decider.explanation))
+                    .or_default()
+                    .push(&node.node_name);
+            }
+        }
+
+        if !groups.is_empty() || !other_decisions.is_empty() {
+            lines.push("Node decisions:".to_string());
+            for ((decider, decision, explanation), nodes) in groups {
+                lines.push(format!(
+                    "- {decider} ({decision}) on {}: {explanation}",
+                    node_list(&nodes)
+                ));
+            }
+            for (decision, nodes) in other_decisions {
+                lines.push(format!("- {decision} on {}", node_list(&nodes)));
+            }
+        }
+        if !possible_nodes.is_empty() {
+            lines.push(format!(
+                "Nodes that can receive the shard: {}",
+                node_list(&possible_nodes)
+            ));
+        }
+
+        lines.join("\n")
+    }
+}
+
+fn node_list(nodes: &[&str]) -> String {
+    let count = if nodes.len() == 1 {
+        "1 node".to_string()
+    } else {
+        format!("{} nodes", nodes.len())
+    };
+    let mut names = nodes
+        .iter()
+        .take(MAX_LISTED_NODES)
+        .copied()
+        .collect::<Vec<_>>()
+        .join(", ");
+    if nodes.len() > MAX_LISTED_NODES {
+        names.push_str(", ...");
+    }
+    format!("{count} ({names})")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_cat_responses() -> anyhow::Result<()> {
+        let nodes: Vec<CatNodesResponse> = serde_json::from_str(
+            r#"[{
+                "name": "node-1", "node.role": "cdfhilmrstw", "master": "*",
+                "heap.percent": "45", "heap.current": "1.2gb", "heap.max": "4gb",
+                "ram.percent": "92", "cpu": "7", "load_1m": "1.53",
+                "disk.used_percent": "61.25", "disk.used": "122.5gb", "disk.total": "200gb"
+            }]"#,
+        )?;
+        assert_eq!(nodes[0].heap_percent, Some(45));
+        assert_eq!(nodes[0].load_1m, Some(1.53));
+
+        let allocation: Vec<CatAllocationResponse> = serde_json::from_str(
+            r#"[
+                { "node": "node-1", "shards": "12", "disk.indices": "1gb", "disk.used": "122.5gb",
+                  "disk.avail": "77.5gb", "disk.total": "200gb", "disk.percent": "61" },
+                { "node": "UNASSIGNED", "shards": "2", "disk.indices": null, "disk.used": null,
+                  "disk.avail": null, "disk.total": null, "disk.percent": null }
+            ]"#,
+        )?;
+        assert_eq!(allocation[1].shards, 2);
+        assert_eq!(allocation[1].disk_percent, None);
+        Ok(())
+    }
+
+    #[test]
+    fn allocation_summary() -> anyhow::Result<()> {
+        let response: AllocationExplainResponse = serde_json::from_str(
+            r#"{
+                "index": "logs",
+                "shard": 0,
+                "primary": false,
+                "current_state": "unassigned",
+                "unassigned_info": { "reason": "NODE_LEFT", "at": "2025-06-01T10:00:00.000Z", "last_allocation_status": "no_attempt" },
+                "can_allocate": "no",
+                "allocate_explanation": "Elasticsearch isn't allowed to allocate this shard to any of the nodes in the cluster.",
+                "node_allocation_decisions": [
+                    { "node_name": "node-1", "node_decision": "no", "deciders": [
+                        { "decider": "same_shard", "decision": "NO", "explanation": "a copy of this shard is already allocated to this node" }
+                    ]},
+                    { "node_name": "node-2", "node_decision": "no", "deciders": [
+                        { "decider": "disk_threshold", "decision": "NO", "explanation": "the node is above the low watermark" }
+                    ]},
+                    { "node_name": "node-3", "node_decision": "no", "deciders": [
+                        { "decider": "disk_threshold", "decision": "NO", "explanation": "the node is above the low watermark" }
+                    ]}
+                ]
+            }"#,
+        )?;
+
+        assert_eq!(
+            response.summary(),
+            "Shard 0 of index logs (replica) is unassigned\n\
+             Unassigned since 2025-06-01T10:00:00.000Z, reason: NODE_LEFT\n\
+             Can allocate: no. Elasticsearch isn't allowed to allocate this shard to any of the nodes in the cluster.\n\
+             Node decisions:\n\
+             - same_shard (NO) on 1 node (node-1): a copy of this shard is already allocated to this node\n\
+             - disk_threshold (NO) on 2 nodes (node-2, node-3): the node is above the low watermark"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn allocation_summary_node_decisions() -> anyhow::Result<()> {
+        let response: AllocationExplainResponse = serde_json::from_str(
+            r#"{
+                "index": "logs",
+                "shard": 1,
+                "primary": true,
+                "current_state": "started",
+                "current_node": { "name": "node-1" },
+                "can_remain_on_current_node": "yes",
+                "can_rebalance_cluster": "yes",
+                "node_allocation_decisions": [
+                    { "node_name": "node-2", "node_decision": "worse_balance" },
+                    { "node_name": "node-3", "node_decision": "throttled", "deciders": [] },
+                    { "node_name": "node-4", "node_decision": "yes" }
+                ]
+            }"#,
+        )?;
+
+        assert_eq!(
+            response.summary(),
+            "Shard 1 of index logs (primary) is started on node node-1\n\
+             Can remain on current node: yes\n\
+             Can rebalance: yes\n\
+             Node decisions:\n\
+             - worse_balance on 1 node (node-2)\n\
+             - throttled on 1 node (node-3)\n\
+             Nodes that can receive the shard: 1 node (node-4)"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn no_unassigned_shards_error() {
+        let error = EsError::from_response(
+            StatusCode::BAD_REQUEST,
+            r#"{ "error": { "type": "illegal_argument_exception", "reason": "No shard was specified in the request which means the response should explain a randomly-chosen unassigned shard, but there are no unassigned shards in this cluster." }, "status": 400 }"#,
+        );
+        assert!(is_no_unassigned_shards(&error));
+
+        let error = EsError::from_response(
+            StatusCode::BAD_REQUEST,
+            r#"{ "error": { "type": "illegal_argument_exception", "reason": "unable to find any unassigned shards to explain [ClusterAllocationExplainRequest[useAnyUnassignedShard=true]]" }, "status": 400 }"#,
+        );
+        assert!(is_no_unassigned_shards(&error));
+
+        let error = EsError::from_response(
+            StatusCode::BAD_REQUEST,
+            r#"{ "error": { "type": "x_content_parse_exception", "reason": "unknown field [shards]" }, "status": 400 }"#,
+        );
+        assert!(!is_no_unassigned_shards(&error));
+    }
+}
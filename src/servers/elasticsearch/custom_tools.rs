@@ -0,0 +1,264 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Custom tools defined in the configuration file.
+
+use crate::servers::elasticsearch::base_tools::{
+    EsqlQueryRequest, EsqlQueryResponse, SearchResult, SearchTemplateRequest,
+};
+use crate::servers::elasticsearch::{
+    CustomTool, EsClientProvider, EsqlTool, SearchTemplate, SearchTemplateTool, ToolBase, read_json,
+};
+use elasticsearch::SearchTemplateParts;
+use futures::FutureExt;
+use indexmap::IndexMap;
+use rmcp::handler::server::router::tool::{ToolRoute, ToolRouter};
+use rmcp::handler::server::tool::ToolCallContext;
+use rmcp::model::{CallToolResult, JsonObject, Tool, ToolAnnotations};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Create a tool router for the custom tools defined in the configuration.
+pub fn tool_router<S: Send + Sync + 'static>(
+    es_client: &EsClientProvider,
+    tools: HashMap<String, CustomTool>,
+) -> anyhow::Result<ToolRouter<S>> {
+    let mut router = ToolRouter::new();
+
+    for (name, tool) in tools.into_iter() {
+        match tool {
+            CustomTool::Esql(esql) => router.add_route(esql_route(name, es_client.clone(), esql)),
+            CustomTool::SearchTemplate(template) => {
+                router.add_route(search_template_route(name, es_client.clone(), template)?)
+            }
+        }
+    }
+
+    Ok(router)
+}
+
+/// Build the MCP tool definition from a custom tool's configuration.
+fn tool_attr(name: String, base: &ToolBase) -> Tool {
+    let annotations = base
+        .annotations
+        .clone()
+        .unwrap_or_else(|| ToolAnnotations::with_title(name.clone()).read_only(true));
+
+    Tool::new(name, base.description.clone(), input_schema(&base.parameters)).annotate(annotations)
+}
+
+/// Create a JSON schema for the tool's input. All parameters are required.
+fn input_schema(parameters: &IndexMap<String, schemars::schema::SchemaObject>) -> JsonObject {
+    let mut schema = JsonObject::new();
+    schema.insert("type".to_string(), json!("object"));
+    schema.insert("properties".to_string(), json!(parameters));
+    schema.insert("required".to_string(), json!(parameters.keys().collect::<Vec<_>>()));
+    schema
+}
+
+/// Extract the tool's parameters from the call arguments, in the order they're defined in the configuration.
+/// Arguments that aren't defined as parameters are ignored.
+fn tool_params(
+    parameters: &IndexMap<String, schemars::schema::SchemaObject>,
+    mut arguments: JsonObject,
+) -> Result<Vec<(String, Value)>, rmcp::Error> {
+    parameters
+        .keys()
+        .map(|name| match arguments.remove(name) {
+            Some(value) => Ok((name.clone(), value)),
+            None => Err(rmcp::Error::invalid_params(format!("missing parameter '{name}'"), None)),
+        })
+        .collect()
+}
+
+//-------------------------------------------------------------------------------------------------
+// ES|QL tools
+
+fn esql_route<S: Send + Sync + 'static>(name: String, es_client: EsClientProvider, tool: EsqlTool) -> ToolRoute<S> {
+    let attr = tool_attr(name, &tool.base);
+    let tool = Arc::new(tool);
+
+    ToolRoute::new_dyn(attr, move |ctx: ToolCallContext<'_, S>| {
+        let es_client = es_client.clone();
+        let tool = tool.clone();
+        async move {
+            let es_client = es_client.get(ctx.request_context);
+            let params = esql_params(&tool.base.parameters, ctx.arguments.unwrap_or_default())?;
+
+            let request = EsqlQueryRequest {
+                query: tool.query.clone(),
+                params,
+                ..Default::default()
+            };
+
+            let response = es_client.esql().query().body(request).send().await;
+            let response: EsqlQueryResponse = read_json(response).await?;
+
+            Ok(CallToolResult::success(response.into_contents(tool.format)?))
+        }
+        .boxed()
+    })
+}
+
+/// Convert tool arguments to ES|QL named parameters.
+fn esql_params(
+    parameters: &IndexMap<String, schemars::schema::SchemaObject>,
+    arguments: JsonObject,
+) -> Result<Vec<Value>, rmcp::Error> {
+    let params = tool_params(parameters, arguments)?;
+    Ok(params.into_iter().map(|(name, value)| json!({ name: value })).collect())
+}
+
+//-------------------------------------------------------------------------------------------------
+// Search template tools
+
+fn search_template_route<S: Send + Sync + 'static>(
+    name: String,
+    es_client: EsClientProvider,
+    tool: SearchTemplateTool,
+) -> anyhow::Result<ToolRoute<S>> {
+    if tool.index.is_empty() {
+        anyhow::bail!("Search template tool '{name}' must have at least one index in 'index'");
+    }
+    let attr = tool_attr(name, &tool.base);
+    let tool = Arc::new(tool);
+
+    Ok(ToolRoute::new_dyn(attr, move |ctx: ToolCallContext<'_, S>| {
+        let es_client = es_client.clone();
+        let tool = tool.clone();
+        async move {
+            let es_client = es_client.get(ctx.request_context);
+            let params = tool_params(&tool.base.parameters, ctx.arguments.unwrap_or_default())?;
+
+            let request = search_template_request(&tool.template, params.into_iter().collect());
+
+            let indices: Vec<&str> = tool.index.iter().map(|s| s.as_str()).collect();
+            let response = es_client
+                .search_template(SearchTemplateParts::Index(&indices))
+                .body(request)
+                .send()
+                .await;
+            let response: SearchResult = read_json(response).await?;
+
+            Ok(CallToolResult::success(response.into_contents(tool.include_metadata)?))
+        }
+        .boxed()
+    }))
+}
+
+fn search_template_request(template: &SearchTemplate, params: JsonObject) -> SearchTemplateRequest {
+    match template {
+        SearchTemplate::TemplateId(id) => SearchTemplateRequest {
+            id: Some(id.clone()),
+            source: None,
+            params,
+        },
+        SearchTemplate::Template(source) => SearchTemplateRequest {
+            id: None,
+            source: Some(source.clone()),
+            params,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::servers::elasticsearch::EsqlResultFormat;
+    use elasticsearch::Elasticsearch;
+
+    fn parameters() -> IndexMap<String, schemars::schema::SchemaObject> {
+        // Parse from a string to preserve property order
+        serde_json::from_str(r#"{ "value": { "type": "number" }, "name": { "type": "string" } }"#).unwrap()
+    }
+
+    #[test]
+    fn schema_requires_all_parameters() {
+        let schema = input_schema(&parameters());
+        assert_eq!(schema["type"], "object");
+        assert_eq!(schema["properties"]["value"]["type"], "number");
+        assert_eq!(schema["required"], json!(["value", "name"]));
+    }
+
+    #[test]
+    fn esql_params_are_named_and_ordered() -> anyhow::Result<()> {
+        let args = json!({ "name": "foo", "value": 42, "unknown": true });
+        let Value::Object(args) = args else { unreachable!() };
+
+        let params = esql_params(&parameters(), args)?;
+        assert_eq!(json!(params), json!([{ "value": 42 }, { "name": "foo" }]));
+
+        let args = json!({ "name": "foo" });
+        let Value::Object(args) = args else { unreachable!() };
+        assert!(esql_params(&parameters(), args).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn esql_single_value() -> anyhow::Result<()> {
+        let response: EsqlQueryResponse = serde_json::from_value(json!({
+            "columns": [{ "name": "result", "type": "long" }],
+            "values": [[84]],
+        }))?;
+
+        let content = response.into_contents(EsqlResultFormat::Value)?;
+        assert_eq!(content[0].as_text().unwrap().text, "84");
+        Ok(())
+    }
+
+    #[test]
+    fn search_template_body() -> anyhow::Result<()> {
+        let mut params = JsonObject::new();
+        params.insert("param_1".to_string(), json!("foo"));
+
+        let request = search_template_request(&SearchTemplate::TemplateId("my-template".to_string()), params.clone());
+        assert_eq!(
+            serde_json::to_value(request)?,
+            json!({ "id": "my-template", "params": { "param_1": "foo" } })
+        );
+
+        let template = json!({ "query": { "term": { "some-field": "{{param_1}}" } } });
+        let request = search_template_request(&SearchTemplate::Template(template.clone()), params);
+        assert_eq!(
+            serde_json::to_value(request)?,
+            json!({ "source": template, "params": { "param_1": "foo" } })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn search_template_requires_index() -> anyhow::Result<()> {
+        let mut config = json!({
+            "description": "Search logs",
+            "parameters": {},
+            "template_id": "my-template",
+        });
+        assert!(serde_json::from_value::<SearchTemplateTool>(config.clone()).is_err());
+
+        let es_client = EsClientProvider::new(Elasticsearch::default());
+
+        config["index"] = json!([]);
+        let tool = serde_json::from_value(config.clone())?;
+        assert!(search_template_route::<()>("empty".to_string(), es_client.clone(), tool).is_err());
+
+        config["index"] = json!(["logs-*"]);
+        let tool = serde_json::from_value(config)?;
+        assert!(search_template_route::<()>("logs".to_string(), es_client, tool).is_ok());
+        Ok(())
+    }
+}
@@ -0,0 +1,401 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Tools that get documents by id, and tools that count documents or check their existence
+//! without fetching them.
+
+use crate::servers::elasticsearch::base_tools::EsBaseTools;
+use crate::servers::elasticsearch::errors::EsError;
+use crate::servers::elasticsearch::{handle_error, internal_error, read_json};
+use elasticsearch::http::StatusCode;
+use elasticsearch::indices::IndicesExistsParts;
+use elasticsearch::{CountParts, ExistsParts, GetParts, MgetParts};
+use rmcp::RoleServer;
+use rmcp::handler::server::tool::Parameters;
+use rmcp::model::{CallToolResult, Content};
+use rmcp::service::RequestContext;
+use rmcp_macros::{tool, tool_router};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct CountParams {
+    /// Name of the Elasticsearch index to count documents in
+    index: String,
+
+    /// Elasticsearch query DSL object with a `query` property, e.g. `{"query": {"term": {...}}}`.
+    /// Other properties are ignored. If missing, all documents are counted.
+    query_body: Option<Map<String, Value>>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct DocumentExistsParams {
+    /// Name of the Elasticsearch index
+    index: String,
+
+    /// Document id
+    id: String,
+
+    /// Routing value, if the document was indexed with custom routing (optional)
+    routing: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct GetDocumentParams {
+    /// Name of the Elasticsearch index
+    index: String,
+
+    /// Document id
+    id: String,
+
+    /// Fields of `_source` to return (optional)
+    source_includes: Option<Vec<String>>,
+
+    /// Fields of `_source` to leave out (optional)
+    source_excludes: Option<Vec<String>>,
+
+    /// Routing value, if the document was indexed with custom routing (optional)
+    routing: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct MgetParams {
+    /// Documents to get
+    docs: Vec<MgetDoc>,
+
+    /// Fields of `_source` to return (optional)
+    source_includes: Option<Vec<String>>,
+
+    /// Fields of `_source` to leave out (optional)
+    source_excludes: Option<Vec<String>>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+struct MgetDoc {
+    /// Name of the Elasticsearch index
+    #[serde(rename(serialize = "_index"))]
+    index: String,
+
+    /// Document id
+    #[serde(rename(serialize = "_id"))]
+    id: String,
+
+    /// Routing value, if the document was indexed with custom routing (optional)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    routing: Option<String>,
+}
+
+#[tool_router(router = document_tool_router, vis = "pub(super)")]
+impl EsBaseTools {
+    //---------------------------------------------------------------------------------------------
+    /// Tool: get a document
+    #[tool(
+        description = "Get a document by id.",
+        annotations(title = "Get ES document", read_only_hint = true)
+    )]
+    async fn get_document(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(GetDocumentParams {
+            index,
+            id,
+            source_includes,
+            source_excludes,
+            routing,
+        }): Parameters<GetDocumentParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let includes = str_vec(&source_includes);
+        let excludes = str_vec(&source_excludes);
+
+        let mut request = es_client.get(GetParts::IndexId(&index, &id));
+        if !includes.is_empty() {
+            request = request._source_includes(&includes);
+        }
+        if !excludes.is_empty() {
+            request = request._source_excludes(&excludes);
+        }
+        if let Some(routing) = &routing {
+            request = request.routing(routing);
+        }
+        let response = request.send().await;
+
+        // A missing document is a 404 response with `"found": false`, and a missing index a 404
+        // response with an error.
+        let response: GetResponse = match response {
+            Ok(response) if response.status_code() == StatusCode::NOT_FOUND => {
+                let body = response.text().await.map_err(internal_error)?;
+                parse_get_not_found(&body)?
+            }
+            response => read_json(response).await?,
+        };
+
+        let text = if response.found {
+            format!("Document {id} of index {index}:")
+        } else {
+            format!("Document {id} not found in index {index}")
+        };
+
+        Ok(CallToolResult::success(vec![
+            Content::text(text),
+            Content::json(response)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: get multiple documents
+    #[tool(
+        description = "Get several documents by index and id in a single request.",
+        annotations(title = "Get multiple ES documents", read_only_hint = true)
+    )]
+    async fn mget(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(MgetParams {
+            docs,
+            source_includes,
+            source_excludes,
+        }): Parameters<MgetParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let includes = str_vec(&source_includes);
+        let excludes = str_vec(&source_excludes);
+
+        let mut request = es_client.mget(MgetParts::None).body(json!({ "docs": docs }));
+        if !includes.is_empty() {
+            request = request._source_includes(&includes);
+        }
+        if !excludes.is_empty() {
+            request = request._source_excludes(&excludes);
+        }
+        let response = request.send().await;
+
+        let response: MgetResponse = read_json(response).await?;
+        let found = response.docs.iter().filter(|doc| doc.found).count();
+
+        Ok(CallToolResult::success(vec![
+            Content::text(format!("Found {found} of {} documents:", response.docs.len())),
+            Content::json(response.docs)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: count documents
+    #[tool(
+        description = "Count the documents matching a query DSL object, without fetching them.",
+        annotations(title = "Count ES documents", read_only_hint = true)
+    )]
+    async fn count(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(CountParams { index, query_body }): Parameters<CountParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let response = es_client
+            .count(CountParts::Index(&[&index]))
+            .body(count_body(query_body)?)
+            .send()
+            .await;
+
+        let response: CountResponse = read_json(response).await?;
+
+        Ok(CallToolResult::success(vec![
+            Content::text(format!("{} documents match", response.count)),
+            Content::json(response)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: check if a document exists
+    #[tool(
+        description = "Check if a document with a given id exists in an index, without fetching it.",
+        annotations(title = "Check if ES document exists", read_only_hint = true)
+    )]
+    async fn document_exists(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(DocumentExistsParams { index, id, routing }): Parameters<DocumentExistsParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let mut request = es_client.exists(ExistsParts::IndexId(&index, &id));
+        if let Some(routing) = &routing {
+            request = request.routing(routing);
+        }
+        let response = request.send().await;
+
+        // A missing document is a 404 response, with no body. So is a missing index.
+        let exists = match response {
+            Ok(response) if response.status_code() == StatusCode::NOT_FOUND => {
+                let response = es_client
+                    .indices()
+                    .exists(IndicesExistsParts::Index(&[&index]))
+                    .send()
+                    .await;
+                match response {
+                    Ok(response) if response.status_code() == StatusCode::NOT_FOUND => {
+                        return Err(EsError::index_not_found(&index).into_rmcp());
+                    }
+                    response => handle_error(response).await?,
+                };
+                false
+            }
+            response => {
+                handle_error(response).await?;
+                true
+            }
+        };
+
+        Ok(CallToolResult::success(vec![Content::json(
+            json!({ "exists": exists }),
+        )?]))
+    }
+}
+
+/// Parse a 404 response of the get API: a missing document, or an error like a missing index.
+fn parse_get_not_found(body: &str) -> Result<GetResponse, rmcp::Error> {
+    serde_json::from_str(body).map_err(|_| EsError::from_response(StatusCode::NOT_FOUND, body).into_rmcp())
+}
+
+/// Count request body. The count API rejects search properties like `size` or `sort`, so only the
+/// query is kept. A body without a query is rejected, as it's likely a query that isn't wrapped
+/// in a `query` property, and counting all documents would give a wrong answer.
+fn count_body(query_body: Option<Map<String, Value>>) -> Result<Value, rmcp::Error> {
+    let Some(mut query_body) = query_body else {
+        return Ok(json!({}));
+    };
+    match query_body.remove("query") {
+        Some(query) => Ok(json!({ "query": query })),
+        None if query_body.is_empty() => Ok(json!({})),
+        None => Err(rmcp::Error::invalid_params(
+            "query_body has no 'query' property. Wrap the query in it, e.g. {\"query\": {\"term\": {...}}}, \
+             or omit query_body to count all documents.",
+            None,
+        )),
+    }
+}
+
+fn str_vec(strings: &Option<Vec<String>>) -> Vec<&str> {
+    strings.iter().flatten().map(String::as_str).collect()
+}
+
+//-------------------------------------------------------------------------------------------------
+// Type definitions for ES responses
+
+/// A document returned by the get and mget APIs
+#[derive(Serialize, Deserialize)]
+pub struct GetResponse {
+    #[serde(rename = "_index")]
+    pub index: String,
+    #[serde(rename = "_id")]
+    pub id: String,
+    #[serde(default)]
+    pub found: bool,
+    #[serde(rename = "_version", default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u64>,
+    #[serde(rename = "_routing", default, skip_serializing_if = "Option::is_none")]
+    pub routing: Option<String>,
+    #[serde(rename = "_source", default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<Value>,
+    /// Per-document error in mget responses, e.g. if the index doesn't exist
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<Value>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct MgetResponse {
+    pub docs: Vec<GetResponse>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CountResponse {
+    pub count: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn count_request_body() -> anyhow::Result<()> {
+        let search_body = json!({
+            "query": { "term": { "status": "error" } },
+            "size": 10,
+            "sort": [{ "@timestamp": "desc" }]
+        });
+        let Value::Object(search_body) = search_body else {
+            unreachable!()
+        };
+
+        assert_eq!(
+            count_body(Some(search_body))?,
+            json!({ "query": { "term": { "status": "error" } } })
+        );
+        assert_eq!(count_body(None)?, json!({}));
+        assert_eq!(count_body(Some(Map::new()))?, json!({}));
+
+        // A query that isn't wrapped in a `query` property
+        let query = json!({ "term": { "status": "error" } });
+        let Value::Object(query) = query else { unreachable!() };
+        assert!(count_body(Some(query)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn mget_docs() -> anyhow::Result<()> {
+        let docs = vec![MgetDoc {
+            index: "logs".to_string(),
+            id: "1".to_string(),
+            routing: None,
+        }];
+        assert_eq!(
+            json!({ "docs": docs }),
+            json!({ "docs": [{ "_index": "logs", "_id": "1" }] })
+        );
+
+        let response: MgetResponse = serde_json::from_str(
+            r#"{ "docs": [
+                { "_index": "logs", "_id": "1", "_version": 1, "found": true, "_source": { "status": "ok" } },
+                { "_index": "logs", "_id": "2", "found": false },
+                { "_index": "nope", "_id": "3", "error": { "type": "index_not_found_exception" } }
+            ]}"#,
+        )?;
+        let found = response.docs.iter().map(|doc| doc.found).collect::<Vec<_>>();
+        assert_eq!(found, vec![true, false, false]);
+        Ok(())
+    }
+
+    #[test]
+    fn get_not_found() -> anyhow::Result<()> {
+        let response = parse_get_not_found(r#"{ "_index": "test", "_id": "1", "found": false }"#)?;
+        assert!(!response.found);
+
+        let error = parse_get_not_found(
+            r#"{ "error": { "type": "index_not_found_exception", "reason": "no such index [test]", "index": "test" }, "status": 404 }"#,
+        )
+        .err()
+        .and_then(|err| EsError::from_rmcp(&err))
+        .unwrap();
+        assert_eq!(error.type_, "index_not_found_exception");
+        assert_eq!(error.index.as_deref(), Some("test"));
+        assert!(error.hint.is_some());
+        Ok(())
+    }
+}
@@ -0,0 +1,422 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Elasticsearch errors, returned to the client as tool error results so that it can correct
+//! its request, e.g. fix a query syntax error or a misspelled index name.
+//!
+//! `handle_error` parses error responses into an [`EsError`], which goes through the tools with
+//! the `?` operator as an `rmcp::Error` with a dedicated error code. It is converted back into a
+//! tool error result in `call_tool`, where index name suggestions are added.
+
+use crate::servers::elasticsearch::base_tools::cat_indices;
+use elasticsearch::Elasticsearch;
+use elasticsearch::http::StatusCode;
+use rmcp::model::{CallToolResult, Content, ErrorCode};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Error code of Elasticsearch errors, in the JSON-RPC range reserved for server errors
+const ES_ERROR_CODE: ErrorCode = ErrorCode(-32050);
+
+/// Maximum number of index names suggested for a missing index
+const MAX_SUGGESTIONS: usize = 5;
+
+/// An Elasticsearch error, with its root cause and hints to correct the request
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct EsError {
+    /// HTTP status, missing for client-side errors like timeouts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub reason: String,
+    /// Location of the error in the query, for syntax and ES|QL verification errors
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub col: Option<u32>,
+    /// Missing index, for index not found errors
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hint: Option<String>,
+    /// Existing indices with a name similar to the missing index
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub suggestions: Vec<String>,
+}
+
+impl EsError {
+    /// Parse the body of an error response
+    pub fn from_response(status: StatusCode, body: &str) -> Self {
+        let cause = match serde_json::from_str::<Value>(body) {
+            Ok(Value::Object(mut obj)) => match obj.remove("error") {
+                Some(Value::String(reason)) => ErrorCause::from_reason(reason),
+                Some(error) => serde_json::from_value(error).unwrap_or_default(),
+                None => ErrorCause::default(),
+            },
+            _ => ErrorCause::default(),
+        };
+
+        let mut cause = cause.root_cause();
+        if cause.type_.is_empty() {
+            cause.type_ = "unknown_error".to_string();
+        }
+        let reason = match cause.reason {
+            Some(reason) => reason,
+            None if body.is_empty() => status.canonical_reason().unwrap_or("Unknown error").to_string(),
+            None => body.to_string(),
+        };
+
+        // ES|QL errors have their location in the reason, e.g. "line 1:15: Unknown column [foo]"
+        let (line, col) = match (cause.line, cause.col) {
+            (Some(line), col) => (Some(line), col),
+            _ if matches!(cause.type_.as_str(), "parsing_exception" | "verification_exception") => {
+                esql_location(&reason).unzip()
+            }
+            _ => (None, None),
+        };
+
+        let mut error = EsError {
+            status: Some(status.as_u16()),
+            type_: cause.type_,
+            reason,
+            line,
+            col,
+            index: cause.index,
+            hint: None,
+            suggestions: Vec::new(),
+        };
+        error.hint = error.default_hint();
+        error
+    }
+
+    /// A request that timed out before Elasticsearch responded
+    pub fn timeout(reason: String) -> Self {
+        EsError {
+            status: None,
+            type_: "timeout".to_string(),
+            reason,
+            line: None,
+            col: None,
+            index: None,
+            hint: Some(TIMEOUT_HINT.to_string()),
+            suggestions: Vec::new(),
+        }
+    }
+
+    /// A missing index, for requests whose error responses have no body, like HEAD requests
+    pub fn index_not_found(index: &str) -> Self {
+        let mut error = EsError {
+            status: Some(StatusCode::NOT_FOUND.as_u16()),
+            type_: "index_not_found_exception".to_string(),
+            reason: format!("no such index [{index}]"),
+            line: None,
+            col: None,
+            index: Some(index.to_string()),
+            hint: None,
+            suggestions: Vec::new(),
+        };
+        error.hint = error.default_hint();
+        error
+    }
+
+    fn default_hint(&self) -> Option<String> {
+        let hint = match self.type_.as_str() {
+            "index_not_found_exception" => "Use list_indices to find the names of existing indices.",
+            "parsing_exception" | "x_content_parse_exception" | "json_parse_exception" => {
+                "Fix the syntax of the query at the given line and column, and retry."
+            }
+            "verification_exception" => {
+                "Check the index and field names used in the ES|QL query, e.g. with get_mappings, and retry."
+            }
+            "security_exception" => {
+                "The credentials used don't have the privileges required for this request. Don't retry it."
+            }
+            "es_rejected_execution_exception" | "circuit_breaking_exception" => {
+                "Elasticsearch is overloaded. Retry later, or with a smaller request."
+            }
+            _ if self.type_.contains("timeout") || matches!(self.status, Some(408 | 504)) => TIMEOUT_HINT,
+            _ => return None,
+        };
+        Some(hint.to_string())
+    }
+
+    /// Convert to an error that can be propagated with `?` by the tools
+    pub fn into_rmcp(self) -> rmcp::Error {
+        let data = serde_json::to_value(&self).ok();
+        rmcp::Error::new(ES_ERROR_CODE, self.message(), data)
+    }
+
+    /// Extract an Elasticsearch error from an error returned by a tool
+    pub fn from_rmcp(error: &rmcp::Error) -> Option<Self> {
+        if error.code != ES_ERROR_CODE {
+            return None;
+        }
+        serde_json::from_value(error.data.clone()?).ok()
+    }
+
+    /// Suggest existing indices whose name is close to the missing index
+    pub async fn add_index_suggestions(&mut self, es_client: &Elasticsearch) {
+        let Some(missing) = &self.index else {
+            return;
+        };
+
+        match cat_indices(es_client, "*").await {
+            Ok(indices) => {
+                let names = indices.into_iter().map(|index| index.index).collect::<Vec<_>>();
+                self.suggestions = similar_names(missing, &names);
+            }
+            // Suggestions are a nice-to-have
+            Err(err) => tracing::warn!("Failed to list indices: {err}"),
+        }
+    }
+
+    fn message(&self) -> String {
+        let mut message = format!("Elasticsearch error {}: {}", self.type_, self.reason);
+        if let Some(line) = self.line {
+            message.push_str(&format!(" (line {line}"));
+            if let Some(col) = self.col {
+                message.push_str(&format!(", column {col}"));
+            }
+            message.push(')');
+        }
+        message
+    }
+
+    /// Convert to a tool error result
+    pub fn into_result(self) -> Result<CallToolResult, rmcp::Error> {
+        let mut text = self.message();
+        if !self.suggestions.is_empty() {
+            text.push_str(&format!("\nDid you mean index {}?", self.suggestions.join(", ")));
+        }
+        if let Some(hint) = &self.hint {
+            text.push('\n');
+            text.push_str(hint);
+        }
+
+        Ok(CallToolResult::error(vec![Content::text(text), Content::json(self)?]))
+    }
+}
+
+const TIMEOUT_HINT: &str = "The request timed out. Narrow the query, e.g. with a smaller time range, or use \
+    async_search or esql_async for long-running queries.";
+
+/// Error cause in Elasticsearch error responses
+#[derive(Debug, Default, Deserialize)]
+struct ErrorCause {
+    #[serde(rename = "type", default)]
+    type_: String,
+    #[serde(default)]
+    reason: Option<String>,
+    #[serde(default)]
+    root_cause: Vec<ErrorCause>,
+    #[serde(default)]
+    caused_by: Option<Box<ErrorCause>>,
+    #[serde(default)]
+    line: Option<u32>,
+    #[serde(default)]
+    col: Option<u32>,
+    #[serde(default)]
+    index: Option<String>,
+}
+
+impl ErrorCause {
+    fn from_reason(reason: String) -> Self {
+        ErrorCause {
+            reason: Some(reason),
+            ..Default::default()
+        }
+    }
+
+    /// The first root cause, which is more specific than the top-level error, e.g. a parsing
+    /// error rather than "all shards failed".
+    fn root_cause(mut self) -> ErrorCause {
+        let mut root = if self.root_cause.is_empty() {
+            self
+        } else {
+            let mut root = self.root_cause.swap_remove(0);
+            // The root cause has no location, but the top-level error may have one
+            root.line = root.line.or(self.line);
+            root.col = root.col.or(self.col);
+            root
+        };
+
+        // Some errors only have a `caused_by` chain
+        while root.root_cause.is_empty()
+            && let Some(caused_by) = root.caused_by.take()
+            && caused_by.reason.is_some()
+        {
+            root = *caused_by;
+        }
+        root
+    }
+}
+
+/// Location in ES|QL error messages, e.g. `line 1:15: Unknown column [foo]`
+fn esql_location(reason: &str) -> Option<(u32, u32)> {
+    let (_, rest) = reason.split_once("line ")?;
+    let (line, rest) = rest.split_once(':')?;
+    let col = rest.split(|c: char| !c.is_ascii_digit()).next()?;
+    Some((line.parse().ok()?, col.parse().ok()?))
+}
+
+/// Names close to `name`: at a small edit distance, or that contain it or are contained in it.
+fn similar_names(name: &str, candidates: &[String]) -> Vec<String> {
+    let max_distance = (name.chars().count() / 3).max(2);
+
+    let mut similar = candidates
+        .iter()
+        .filter(|candidate| !candidate.starts_with('.'))
+        .filter_map(|candidate| {
+            let distance = edit_distance(name, candidate);
+            let related = candidate.contains(name) || name.contains(candidate.as_str());
+            (distance <= max_distance || related).then_some((distance, candidate))
+        })
+        .collect::<Vec<_>>();
+
+    similar.sort();
+    similar
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, candidate)| candidate.clone())
+        .collect()
+}
+
+/// Levenshtein distance between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn index_not_found() -> anyhow::Result<()> {
+        let body = r#"{
+            "error": {
+                "root_cause": [{
+                    "type": "index_not_found_exception", "reason": "no such index [logz]",
+                    "index_uuid": "_na_", "index": "logz", "resource.type": "index_or_alias", "resource.id": "logz"
+                }],
+                "type": "index_not_found_exception", "reason": "no such index [logz]",
+                "index_uuid": "_na_", "index": "logz", "resource.type": "index_or_alias", "resource.id": "logz"
+            },
+            "status": 404
+        }"#;
+
+        let error = EsError::from_response(StatusCode::NOT_FOUND, body);
+        assert_eq!(error.type_, "index_not_found_exception");
+        assert_eq!(error.index.as_deref(), Some("logz"));
+        assert!(error.hint.is_some());
+
+        // Roundtrip through the rmcp error
+        let rmcp_error = error.into_rmcp();
+        assert_eq!(
+            rmcp_error.message,
+            "Elasticsearch error index_not_found_exception: no such index [logz]"
+        );
+        let error = EsError::from_rmcp(&rmcp_error).unwrap();
+        assert_eq!(error.index.as_deref(), Some("logz"));
+
+        assert!(EsError::from_rmcp(&rmcp::Error::internal_error("boom", None)).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn query_parsing_error() {
+        let body = r#"{
+            "error": {
+                "root_cause": [{
+                    "type": "parsing_exception", "reason": "unknown query [matc]", "line": 1, "col": 19
+                }],
+                "type": "parsing_exception", "reason": "unknown query [matc]", "line": 1, "col": 19,
+                "caused_by": { "type": "named_object_not_found_exception", "reason": "[1:19] unknown field [matc]" }
+            },
+            "status": 400
+        }"#;
+
+        let error = EsError::from_response(StatusCode::BAD_REQUEST, body);
+        assert_eq!(error.type_, "parsing_exception");
+        assert_eq!(error.reason, "unknown query [matc]");
+        assert_eq!((error.line, error.col), (Some(1), Some(19)));
+    }
+
+    #[test]
+    fn esql_verification_error() {
+        let body = r#"{
+            "error": {
+                "root_cause": [{
+                    "type": "verification_exception",
+                    "reason": "Found 1 problem\nline 1:30: Unknown column [hostname]"
+                }],
+                "type": "verification_exception",
+                "reason": "Found 1 problem\nline 1:30: Unknown column [hostname]"
+            },
+            "status": 400
+        }"#;
+
+        let error = EsError::from_response(StatusCode::BAD_REQUEST, body);
+        assert_eq!(error.type_, "verification_exception");
+        assert_eq!((error.line, error.col), (Some(1), Some(30)));
+
+        // Other errors may mention a line in their reason
+        let body = r#"{
+            "error": { "type": "mapper_parsing_exception", "reason": "failed to parse field [message] at line 3:12" },
+            "status": 400
+        }"#;
+        let error = EsError::from_response(StatusCode::BAD_REQUEST, body);
+        assert_eq!((error.line, error.col), (None, None));
+    }
+
+    #[test]
+    fn non_json_error() {
+        let error = EsError::from_response(StatusCode::GATEWAY_TIMEOUT, "");
+        assert_eq!(error.type_, "unknown_error");
+        assert_eq!(error.reason, "Gateway Timeout");
+        assert!(error.hint.unwrap().contains("timed out"));
+
+        let error = EsError::from_response(
+            StatusCode::METHOD_NOT_ALLOWED,
+            r#"{ "error": "Incorrect HTTP method" }"#,
+        );
+        assert_eq!(error.reason, "Incorrect HTTP method");
+    }
+
+    #[test]
+    fn index_suggestions() {
+        let indices = ["logs-2024", "logs", "metrics", "users", ".security"].map(String::from);
+
+        assert_eq!(similar_names("logz", &indices), vec!["logs"]);
+        assert_eq!(similar_names("log", &indices), vec!["logs", "logs-2024"]);
+        assert_eq!(similar_names("user", &indices), vec!["users"]);
+        assert_eq!(similar_names("security", &indices), Vec::<String>::new());
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+}
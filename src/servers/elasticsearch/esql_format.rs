@@ -0,0 +1,185 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Output formats for ES|QL results. Tabular text formats are much more compact than JSON
+//! objects, which repeat column names in every row.
+
+use crate::servers::elasticsearch::EsqlResultFormat;
+use crate::servers::elasticsearch::base_tools::EsqlQueryResponse;
+use rmcp::model::Content;
+use serde_json::{Value, json};
+use std::borrow::Cow;
+
+impl EsqlQueryResponse {
+    /// Convert the response rows into tool result contents
+    pub fn into_contents(self, format: EsqlResultFormat) -> Result<Vec<Content>, rmcp::Error> {
+        let content = match format {
+            EsqlResultFormat::Json => Content::json(self.into_objects())?,
+            EsqlResultFormat::Columnar => Content::json(json!({
+                "columns": self.columns,
+                "values": self.values,
+            }))?,
+            EsqlResultFormat::Csv => Content::text(self.to_delimited(',')),
+            EsqlResultFormat::Tsv => Content::text(self.to_delimited('\t')),
+            EsqlResultFormat::Markdown => Content::text(self.to_markdown()),
+            EsqlResultFormat::Value => {
+                let mut objects = self.into_objects();
+                match objects.pop() {
+                    // Single row and single column: the value itself
+                    Some(Value::Object(obj)) if objects.is_empty() && obj.len() == 1 => match obj.into_iter().next() {
+                        Some((_, Value::String(s))) => Content::text(s),
+                        Some((_, value)) => Content::json(value)?,
+                        None => unreachable!(),
+                    },
+                    // Single row: the row object
+                    Some(obj) if objects.is_empty() => Content::json(obj)?,
+                    Some(obj) => {
+                        objects.push(obj);
+                        Content::json(objects)?
+                    }
+                    None => Content::json(objects)?,
+                }
+            }
+        };
+
+        Ok(vec![content])
+    }
+
+    fn to_delimited(&self, separator: char) -> String {
+        let mut result = String::new();
+
+        let header = self.columns.iter().map(|c| Cow::from(c.name.as_str()));
+        push_delimited_row(&mut result, separator, header);
+
+        for row in &self.values {
+            push_delimited_row(&mut result, separator, row.iter().map(cell_text));
+        }
+
+        result
+    }
+
+    fn to_markdown(&self) -> String {
+        let mut result = String::new();
+
+        let header = self.columns.iter().map(|c| Cow::from(c.name.as_str()));
+        push_markdown_row(&mut result, header);
+        push_markdown_row(&mut result, self.columns.iter().map(|_| Cow::from("---")));
+
+        for row in &self.values {
+            push_markdown_row(&mut result, row.iter().map(cell_text));
+        }
+
+        result
+    }
+}
+
+/// Text of a cell: strings without quotes, nulls as empty strings, and other values as JSON.
+fn cell_text(value: &Value) -> Cow<'_, str> {
+    match value {
+        Value::Null => Cow::from(""),
+        Value::String(s) => Cow::from(s.as_str()),
+        value => Cow::from(value.to_string()),
+    }
+}
+
+fn push_delimited_row<'a>(result: &mut String, separator: char, cells: impl Iterator<Item = Cow<'a, str>>) {
+    for (i, cell) in cells.enumerate() {
+        if i > 0 {
+            result.push(separator);
+        }
+        if separator == '\t' {
+            // TSV has no quoting: escape special characters
+            result.push_str(&cell.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n"));
+        } else if cell.contains([separator, '"', '\n', '\r']) {
+            result.push('"');
+            result.push_str(&cell.replace('"', "\"\""));
+            result.push('"');
+        } else {
+            result.push_str(&cell);
+        }
+    }
+    result.push('\n');
+}
+
+fn push_markdown_row<'a>(result: &mut String, cells: impl Iterator<Item = Cow<'a, str>>) {
+    result.push('|');
+    for cell in cells {
+        result.push(' ');
+        result.push_str(&cell.replace('|', "\\|").replace('\n', "<br>"));
+        result.push_str(" |");
+    }
+    result.push('\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response() -> EsqlQueryResponse {
+        serde_json::from_value(json!({
+            "columns": [{ "name": "host", "type": "keyword" }, { "name": "message", "type": "text" }],
+            "values": [
+                ["web-1", "GET /index.html"],
+                ["web-2", "a \"quoted\", multi\nline | message"],
+                [null, 42],
+            ],
+        }))
+        .unwrap()
+    }
+
+    fn text(format: EsqlResultFormat) -> anyhow::Result<String> {
+        let contents = response().into_contents(format)?;
+        Ok(contents[0].as_text().unwrap().text.clone())
+    }
+
+    #[test]
+    fn csv_format() -> anyhow::Result<()> {
+        assert_eq!(
+            text(EsqlResultFormat::Csv)?,
+            "host,message\n\
+             web-1,GET /index.html\n\
+             web-2,\"a \"\"quoted\"\", multi\nline | message\"\n\
+             ,42\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn tsv_format() -> anyhow::Result<()> {
+        assert_eq!(
+            text(EsqlResultFormat::Tsv)?,
+            "host\tmessage\n\
+             web-1\tGET /index.html\n\
+             web-2\ta \"quoted\", multi\\nline | message\n\
+             \t42\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn markdown_format() -> anyhow::Result<()> {
+        assert_eq!(
+            text(EsqlResultFormat::Markdown)?,
+            "| host | message |\n\
+             | --- | --- |\n\
+             | web-1 | GET /index.html |\n\
+             | web-2 | a \"quoted\", multi<br>line \\| message |\n\
+             |  | 42 |\n"
+        );
+        Ok(())
+    }
+}
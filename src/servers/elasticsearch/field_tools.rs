@@ -0,0 +1,754 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Tools to discover the fields of indices, what can be done with them, and what their values
+//! look like.
+
+use crate::servers::elasticsearch::base_tools::{EsBaseTools, SearchResult, add_source_fields};
+use crate::servers::elasticsearch::read_json;
+use crate::servers::elasticsearch::response_limits::truncate_strings;
+use elasticsearch::{FieldCapsParts, SearchParts};
+use indexmap::IndexMap;
+use rmcp::RoleServer;
+use rmcp::handler::server::tool::Parameters;
+use rmcp::model::{CallToolResult, Content};
+use rmcp::service::RequestContext;
+use rmcp_macros::{tool, tool_router};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+
+/// Number of sample documents, if not specified
+const DEFAULT_SAMPLE_SIZE: u64 = 5;
+
+/// Maximum number of sample documents
+const MAX_SAMPLE_SIZE: u64 = 50;
+
+/// Maximum length of strings in sample documents, if not specified
+const DEFAULT_MAX_STRING_LENGTH: usize = 200;
+
+/// Number of top values in a field distribution, if not specified
+const DEFAULT_TOP_VALUES: u64 = 10;
+
+/// Maximum number of top values in a field distribution
+const MAX_TOP_VALUES: u64 = 100;
+
+/// Percentiles computed for numeric and date fields
+const PERCENTS: [f64; 7] = [1.0, 5.0, 25.0, 50.0, 75.0, 95.0, 99.0];
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct FieldCapsParams {
+    /// Name or pattern of the Elasticsearch indices, e.g. `logs-*`
+    index: String,
+
+    /// Field name or pattern, e.g. `host.*` (optional, defaults to all fields)
+    field: Option<String>,
+
+    /// Only return fields of these types, e.g. `keyword` or `date` (optional)
+    types: Option<Vec<String>>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct SampleDocumentsParams {
+    /// Name or pattern of the Elasticsearch indices
+    index: String,
+
+    /// Number of documents to return (optional, defaults to 5, at most 50)
+    size: Option<u64>,
+
+    /// Name of the fields that need to be returned (optional)
+    fields: Option<Vec<String>>,
+
+    /// Start of the time range, e.g. `now-1d` or `2025-01-01` (optional)
+    from: Option<String>,
+
+    /// End of the time range, e.g. `now` (optional)
+    to: Option<String>,
+
+    /// Date field used for the time range (optional, defaults to `@timestamp`)
+    time_field: Option<String>,
+
+    /// Random seed, to get the same sample again (optional)
+    seed: Option<u64>,
+
+    /// Maximum length of string values, longer values are truncated (optional, defaults to 200)
+    max_string_length: Option<usize>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct FieldDistributionParams {
+    /// Name or pattern of the Elasticsearch indices
+    index: String,
+
+    /// Name of the field
+    field: String,
+
+    /// Query DSL object to select the documents (optional, defaults to all documents)
+    query: Option<Map<String, Value>>,
+
+    /// Number of top values to return (optional, defaults to 10, at most 100)
+    size: Option<u64>,
+}
+
+#[tool_router(router = field_tool_router, vis = "pub(super)")]
+impl EsBaseTools {
+    //---------------------------------------------------------------------------------------------
+    /// Tool: field capabilities
+    #[tool(
+        description = "Get the fields of the indices matching a pattern, with their type and whether they are searchable and aggregatable. Fields whose type differ across indices are reported with the indices of each type. Use it before writing aggregations over several indices.",
+        annotations(title = "Get ES field capabilities", read_only_hint = true)
+    )]
+    async fn field_caps(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(FieldCapsParams { index, field, types }): Parameters<FieldCapsParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let field = field.unwrap_or("*".to_string());
+        let response = es_client
+            .field_caps(FieldCapsParts::Index(&[&index]))
+            .fields(&[&field])
+            .send()
+            .await;
+        let response: FieldCapsResponse = read_json(response).await?;
+
+        let index_count = response.indices.len();
+        let fields = response.into_fields(types.as_deref().unwrap_or_default());
+        let conflicts = fields.iter().filter(|f| !f.conflicts.is_empty()).count();
+
+        let mut contents = vec![Content::text(format!(
+            "{} fields in {index_count} indices matching '{index}':",
+            fields.len()
+        ))];
+        if conflicts > 0 {
+            contents.push(Content::text(format!(
+                "{conflicts} fields have different types across indices, see 'conflicts'"
+            )));
+        }
+        contents.push(Content::json(fields)?);
+
+        Ok(CallToolResult::success(contents))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: sample documents
+    #[tool(
+        description = "Get a few random documents from an index, optionally in a time range, to see real field values and their format. Complements get_mappings. Long values are truncated.",
+        annotations(title = "Sample ES documents", read_only_hint = true)
+    )]
+    async fn sample_documents(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<SampleDocumentsParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let response = es_client
+            .search(SearchParts::Index(&[&params.index]))
+            .body(sample_body(&params))
+            .send()
+            .await;
+        let mut response: SearchResult = read_json(response).await?;
+
+        let max_length = params.max_string_length.unwrap_or(DEFAULT_MAX_STRING_LENGTH);
+        let mut shortened = 0;
+        for hit in &mut response.hits.hits {
+            if let Some(source) = &mut hit.source {
+                shortened += truncate_strings(source, max_length);
+            }
+        }
+
+        let mut contents = response.into_contents(false)?;
+        if shortened > 0 {
+            contents.push(Content::text(format!(
+                "{shortened} values longer than {max_length} characters were truncated"
+            )));
+        }
+
+        Ok(CallToolResult::success(contents))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: field value distribution
+    #[tool(
+        description = "Get the distribution of the values of a field: top values with their count, number of distinct values and of documents missing the field, and min, max and percentiles for numeric and date fields. Aggregations are chosen from the field type, and the keyword sub-field of text fields is used.",
+        annotations(title = "ES field value distribution", read_only_hint = true)
+    )]
+    async fn field_distribution(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(FieldDistributionParams {
+            index,
+            field,
+            query,
+            size,
+        }): Parameters<FieldDistributionParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        // Text fields can't be aggregated, but often have a keyword sub-field
+        let keyword = format!("{field}.keyword");
+        let response = es_client
+            .field_caps(FieldCapsParts::Index(&[&index]))
+            .fields(&[&field, &keyword])
+            .send()
+            .await;
+        let response: FieldCapsResponse = read_json(response).await?;
+
+        let agg_field = match AggField::resolve(&field, &response.fields) {
+            Ok(agg_field) => agg_field,
+            Err(message) => return Ok(CallToolResult::error(vec![Content::text(message)])),
+        };
+
+        let size = size.unwrap_or(DEFAULT_TOP_VALUES).min(MAX_TOP_VALUES);
+        let mut body = json!({
+            "size": 0,
+            "track_total_hits": true,
+            "aggs": agg_field.aggs(size),
+        });
+        if let Some(query) = query {
+            body["query"] = Value::Object(query);
+        }
+
+        let response = es_client.search(SearchParts::Index(&[&index])).body(body).send().await;
+        let response: SearchResult = read_json(response).await?;
+
+        let mut contents = Vec::new();
+        if let Some(note) = &agg_field.note {
+            contents.push(Content::text(note));
+        }
+        contents.push(Content::json(agg_field.distribution(response))?);
+
+        Ok(CallToolResult::success(contents))
+    }
+}
+
+/// Search request returning random documents. Scores are replaced by a random value, that is
+/// reproducible with a seed.
+fn sample_body(params: &SampleDocumentsParams) -> Map<String, Value> {
+    let query = match (&params.from, &params.to) {
+        (None, None) => json!({ "match_all": {} }),
+        (from, to) => {
+            let mut range = Map::new();
+            if let Some(from) = from {
+                range.insert("gte".to_string(), json!(from));
+            }
+            if let Some(to) = to {
+                range.insert("lte".to_string(), json!(to));
+            }
+            let time_field = params.time_field.as_deref().unwrap_or("@timestamp");
+            json!({ "range": { time_field: range } })
+        }
+    };
+
+    let random_score = match params.seed {
+        // A seeded random score needs a field with distinct values per document
+        Some(seed) => json!({ "seed": seed, "field": "_seq_no" }),
+        None => json!({}),
+    };
+
+    let size = params.size.unwrap_or(DEFAULT_SAMPLE_SIZE).min(MAX_SAMPLE_SIZE);
+    let Value::Object(mut body) = json!({
+        "size": size,
+        "query": {
+            "function_score": {
+                "query": query,
+                "random_score": random_score,
+                "boost_mode": "replace"
+            }
+        }
+    }) else {
+        unreachable!()
+    };
+
+    add_source_fields(&mut body, params.fields.clone());
+    body
+}
+
+/// Capabilities of a field across all indices
+#[derive(Debug, PartialEq, Serialize)]
+pub struct FieldSummary {
+    pub name: String,
+    /// The field type, or `conflict` if it differs across indices
+    #[serde(rename = "type")]
+    pub type_: String,
+    /// Searchable in all indices
+    pub searchable: bool,
+    /// Aggregatable in all indices
+    pub aggregatable: bool,
+    /// Indices for each type, if the type differs across indices
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    pub conflicts: IndexMap<String, Vec<String>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FieldKind {
+    Numeric,
+    Date,
+    /// Fields whose values are terms, like keywords or IP addresses
+    Term,
+}
+
+impl FieldKind {
+    /// The kind of a field type, or `None` if the field distribution aggregations aren't supported on
+    /// it, e.g. for `geo_point` or `histogram` that have no terms.
+    fn of(type_: &str) -> Option<FieldKind> {
+        match type_ {
+            "long" | "integer" | "short" | "byte" | "double" | "float" | "half_float" | "scaled_float"
+            | "unsigned_long" => Some(FieldKind::Numeric),
+            "date" | "date_nanos" => Some(FieldKind::Date),
+            "keyword" | "constant_keyword" | "wildcard" | "ip" | "boolean" | "version" | "flattened" => {
+                Some(FieldKind::Term)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The field that is aggregated to compute a field distribution
+#[derive(Debug, PartialEq)]
+struct AggField {
+    name: String,
+    type_: String,
+    kind: FieldKind,
+    /// Explains the choice of the field or aggregations
+    note: Option<String>,
+}
+
+impl AggField {
+    /// Choose the field and aggregations from the field capabilities, or return an error message
+    fn resolve(field: &str, caps: &IndexMap<String, IndexMap<String, FieldCaps>>) -> Result<AggField, String> {
+        let Some(field_caps) = caps.get(field) else {
+            return Err(format!(
+                "Field '{field}' doesn't exist in the matching indices. Use field_caps to list the fields."
+            ));
+        };
+
+        let type_ = field_caps.keys().cloned().collect::<Vec<_>>().join(", ");
+        let kinds = field_caps.keys().map(|t| FieldKind::of(t)).collect::<Vec<_>>();
+        let aggregatable = field_caps.values().all(|c| c.aggregatable);
+
+        if aggregatable {
+            let unsupported = field_caps
+                .keys()
+                .filter(|t| FieldKind::of(t).is_none())
+                .cloned()
+                .collect::<Vec<_>>();
+            if !unsupported.is_empty() {
+                return Err(format!(
+                    "Distribution is not supported for field '{field}' of type {}. Use search with aggregations suited to this type.",
+                    unsupported.join(", ")
+                ));
+            }
+
+            let kind = kinds[0].unwrap_or(FieldKind::Term);
+            if kinds.iter().all(|k| *k == Some(kind)) {
+                return Ok(AggField {
+                    name: field.to_string(),
+                    type_,
+                    kind,
+                    note: None,
+                });
+            }
+            return Ok(AggField {
+                name: field.to_string(),
+                type_,
+                kind: FieldKind::Term,
+                note: Some(format!(
+                    "Field '{field}' has different types across indices, only top values are computed"
+                )),
+            });
+        }
+
+        let keyword = format!("{field}.keyword");
+        if let Some(keyword_caps) = caps.get(&keyword)
+            && keyword_caps.len() == 1
+            && keyword_caps.values().all(|c| c.aggregatable)
+        {
+            return Ok(AggField {
+                note: Some(format!(
+                    "Field '{field}' of type {type_} can't be aggregated, using its sub-field '{keyword}'"
+                )),
+                name: keyword,
+                type_: "keyword".to_string(),
+                kind: FieldKind::Term,
+            });
+        }
+
+        Err(format!(
+            "Field '{field}' of type {type_} can't be aggregated and has no keyword sub-field. Use field_caps to find aggregatable fields."
+        ))
+    }
+
+    fn aggs(&self, size: u64) -> Value {
+        let field = &self.name;
+        let mut aggs = json!({
+            "missing": { "missing": { "field": field } },
+            "cardinality": { "cardinality": { "field": field } },
+            "top_values": { "terms": { "field": field, "size": size } },
+        });
+        if self.kind != FieldKind::Term {
+            aggs["min"] = json!({ "min": { "field": field } });
+            aggs["max"] = json!({ "max": { "field": field } });
+            aggs["percentiles"] = json!({ "percentiles": { "field": field, "percents": PERCENTS } });
+        }
+        if self.kind == FieldKind::Numeric {
+            aggs["avg"] = json!({ "avg": { "field": field } });
+        }
+        aggs
+    }
+
+    fn distribution(&self, response: SearchResult) -> FieldDistribution {
+        let aggs = &response.aggregations;
+        let agg = |name: &str| aggs.get(name).unwrap_or(&Value::Null);
+
+        let top_values = agg("top_values")["buckets"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|bucket| TermCount {
+                value: bucket_value(bucket),
+                count: bucket["doc_count"].as_u64().unwrap_or_default(),
+            })
+            .collect();
+
+        FieldDistribution {
+            field: self.name.clone(),
+            type_: self.type_.clone(),
+            documents: response.hits.total.map(|t| t.value),
+            missing: agg("missing")["doc_count"].as_u64(),
+            cardinality: agg("cardinality")["value"].as_u64(),
+            top_values,
+            other_values_count: agg("top_values")["sum_other_doc_count"].as_u64().filter(|c| *c > 0),
+            min: metric_value(agg("min")),
+            max: metric_value(agg("max")),
+            avg: metric_value(agg("avg")),
+            percentiles: percentile_values(agg("percentiles")),
+        }
+    }
+}
+
+/// Key of a terms bucket, formatted for dates and booleans
+fn bucket_value(bucket: &Value) -> Value {
+    bucket.get("key_as_string").unwrap_or(&bucket["key"]).clone()
+}
+
+/// Value of a metric aggregation, formatted for dates. Null if there are no values.
+fn metric_value(agg: &Value) -> Option<Value> {
+    match agg.get("value_as_string") {
+        Some(value) => Some(value.clone()),
+        None => agg.get("value").filter(|v| !v.is_null()).cloned(),
+    }
+}
+
+/// Percentile values, formatted for dates
+fn percentile_values(agg: &Value) -> Option<IndexMap<String, Value>> {
+    let values = agg.get("values")?.as_object()?;
+    let percentiles = values
+        .iter()
+        .filter(|(percent, value)| !percent.ends_with("_as_string") && !value.is_null())
+        .map(|(percent, value)| {
+            let formatted = values.get(&format!("{percent}_as_string")).unwrap_or(value);
+            (percent.clone(), formatted.clone())
+        })
+        .collect::<IndexMap<_, _>>();
+    (!percentiles.is_empty()).then_some(percentiles)
+}
+
+/// Distribution of the values of a field
+#[derive(Debug, Serialize)]
+pub struct FieldDistribution {
+    pub field: String,
+    #[serde(rename = "type")]
+    pub type_: String,
+    /// Number of matching documents
+    pub documents: Option<u64>,
+    /// Number of documents without a value
+    pub missing: Option<u64>,
+    /// Approximate number of distinct values
+    pub cardinality: Option<u64>,
+    pub top_values: Vec<TermCount>,
+    /// Number of values that are not in the top values
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub other_values_count: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avg: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub percentiles: Option<IndexMap<String, Value>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TermCount {
+    pub value: Value,
+    pub count: u64,
+}
+
+//-------------------------------------------------------------------------------------------------
+// Type definitions for ES responses
+
+#[derive(Serialize, Deserialize)]
+pub struct FieldCapsResponse {
+    pub indices: Vec<String>,
+    /// Capabilities by field name, and then by type
+    pub fields: IndexMap<String, IndexMap<String, FieldCaps>>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct FieldCaps {
+    #[serde(default)]
+    pub metadata_field: bool,
+    pub searchable: bool,
+    pub aggregatable: bool,
+    /// Indices that have this type, if the field has several types
+    #[serde(default)]
+    pub indices: Vec<String>,
+}
+
+impl FieldCapsResponse {
+    /// Summarize field capabilities. Metadata fields like `_id` and object fields are left out.
+    /// If `types` isn't empty, only fields with one of these types are kept.
+    pub fn into_fields(self, types: &[String]) -> Vec<FieldSummary> {
+        let mut fields = self
+            .fields
+            .into_iter()
+            .filter(|(_, caps)| !caps.values().any(|c| c.metadata_field))
+            .filter(|(_, caps)| !caps.contains_key("object"))
+            .filter(|(_, caps)| types.is_empty() || caps.keys().any(|t| types.contains(t)))
+            .map(|(name, caps)| {
+                let type_ = match caps.len() {
+                    1 => caps.keys().next().cloned().unwrap_or_default(),
+                    _ => "conflict".to_string(),
+                };
+                FieldSummary {
+                    name,
+                    type_,
+                    searchable: caps.values().all(|c| c.searchable),
+                    aggregatable: caps.values().all(|c| c.aggregatable),
+                    conflicts: match caps.len() {
+                        1 => IndexMap::new(),
+                        _ => caps.into_iter().map(|(t, c)| (t, c.indices)).collect(),
+                    },
+                }
+            })
+            .collect::<Vec<_>>();
+
+        fields.sort_by(|a, b| a.name.cmp(&b.name));
+        fields
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response() -> anyhow::Result<FieldCapsResponse> {
+        Ok(serde_json::from_str(
+            r#"{
+                "indices": ["logs-1", "logs-2"],
+                "fields": {
+                    "_id": { "_id": { "type": "_id", "metadata_field": true, "searchable": true, "aggregatable": false } },
+                    "host": { "object": { "type": "object", "metadata_field": false, "searchable": false, "aggregatable": false } },
+                    "host.name": { "keyword": { "type": "keyword", "metadata_field": false, "searchable": true, "aggregatable": true } },
+                    "message": { "text": { "type": "text", "metadata_field": false, "searchable": true, "aggregatable": false } },
+                    "status": {
+                        "keyword": { "type": "keyword", "metadata_field": false, "searchable": true, "aggregatable": true, "indices": ["logs-1"] },
+                        "long": { "type": "long", "metadata_field": false, "searchable": true, "aggregatable": true, "indices": ["logs-2"] }
+                    }
+                }
+            }"#,
+        )?)
+    }
+
+    #[test]
+    fn field_summaries() -> anyhow::Result<()> {
+        let fields = response()?.into_fields(&[]);
+
+        let names = fields.iter().map(|f| f.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["host.name", "message", "status"]);
+
+        assert_eq!(
+            fields[2],
+            FieldSummary {
+                name: "status".to_string(),
+                type_: "conflict".to_string(),
+                searchable: true,
+                aggregatable: true,
+                conflicts: IndexMap::from([
+                    ("keyword".to_string(), vec!["logs-1".to_string()]),
+                    ("long".to_string(), vec!["logs-2".to_string()]),
+                ]),
+            }
+        );
+        assert!(!fields[1].aggregatable);
+        Ok(())
+    }
+
+    #[test]
+    fn sample_request_body() -> anyhow::Result<()> {
+        let params: SampleDocumentsParams = serde_json::from_value(json!({
+            "index": "logs-*",
+            "size": 500,
+            "fields": ["message"],
+            "from": "now-1d",
+            "seed": 42
+        }))?;
+
+        assert_eq!(
+            Value::Object(sample_body(&params)),
+            json!({
+                "size": 50,
+                "query": {
+                    "function_score": {
+                        "query": { "range": { "@timestamp": { "gte": "now-1d" } } },
+                        "random_score": { "seed": 42, "field": "_seq_no" },
+                        "boost_mode": "replace"
+                    }
+                },
+                "_source": ["message"]
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn distribution_field() -> anyhow::Result<()> {
+        let response: FieldCapsResponse = serde_json::from_str(
+            r#"{
+                "indices": ["logs"],
+                "fields": {
+                    "bytes": { "long": { "type": "long", "searchable": true, "aggregatable": true } },
+                    "message": { "text": { "type": "text", "searchable": true, "aggregatable": false } },
+                    "message.keyword": { "keyword": { "type": "keyword", "searchable": true, "aggregatable": true } },
+                    "body": { "text": { "type": "text", "searchable": true, "aggregatable": false } }
+                }
+            }"#,
+        )?;
+
+        let bytes = AggField::resolve("bytes", &response.fields).unwrap();
+        assert_eq!(bytes.kind, FieldKind::Numeric);
+        assert_eq!(bytes.aggs(10)["percentiles"]["percentiles"]["field"], "bytes");
+        assert_eq!(bytes.aggs(10)["avg"]["avg"]["field"], "bytes");
+
+        let message = AggField::resolve("message", &response.fields).unwrap();
+        assert_eq!(message.name, "message.keyword");
+        assert_eq!(message.kind, FieldKind::Term);
+        assert!(message.note.is_some());
+        assert!(message.aggs(10).get("min").is_none());
+
+        assert!(AggField::resolve("body", &response.fields).is_err());
+        assert!(AggField::resolve("nope", &response.fields).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn distribution_mixed_types() -> anyhow::Result<()> {
+        let response: FieldCapsResponse = serde_json::from_str(
+            r#"{
+                "indices": ["logs-1", "logs-2"],
+                "fields": {
+                    "port": {
+                        "keyword": { "type": "keyword", "searchable": true, "aggregatable": true },
+                        "long": { "type": "long", "searchable": true, "aggregatable": true }
+                    },
+                    "labels": { "flattened": { "type": "flattened", "searchable": true, "aggregatable": true } },
+                    "location": { "geo_point": { "type": "geo_point", "searchable": true, "aggregatable": true } },
+                    "host": {
+                        "keyword": { "type": "keyword", "searchable": true, "aggregatable": true },
+                        "match_only_text": { "type": "match_only_text", "searchable": true, "aggregatable": false }
+                    },
+                    "status": {
+                        "keyword": { "type": "keyword", "searchable": true, "aggregatable": true },
+                        "text": { "type": "text", "searchable": true, "aggregatable": false }
+                    }
+                }
+            }"#,
+        )?;
+
+        let port = AggField::resolve("port", &response.fields).unwrap();
+        assert_eq!(port.name, "port");
+        assert_eq!(port.kind, FieldKind::Term);
+        assert!(port.note.is_some());
+
+        let labels = AggField::resolve("labels", &response.fields).unwrap();
+        assert_eq!(labels.kind, FieldKind::Term);
+        assert!(labels.note.is_none());
+
+        let location = AggField::resolve("location", &response.fields).unwrap_err();
+        assert!(location.contains("not supported"));
+        assert!(location.contains("geo_point"));
+
+        assert!(AggField::resolve("host", &response.fields).is_err());
+        assert!(AggField::resolve("status", &response.fields).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn date_distribution() -> anyhow::Result<()> {
+        let field = AggField {
+            name: "@timestamp".to_string(),
+            type_: "date".to_string(),
+            kind: FieldKind::Date,
+            note: None,
+        };
+        let response: SearchResult = serde_json::from_value(json!({
+            "hits": { "total": { "value": 120 }, "hits": [] },
+            "aggregations": {
+                "missing": { "doc_count": 3 },
+                "cardinality": { "value": 117 },
+                "top_values": {
+                    "sum_other_doc_count": 100,
+                    "buckets": [
+                        { "key": 1735689600000_u64, "key_as_string": "2025-01-01T00:00:00.000Z", "doc_count": 2 }
+                    ]
+                },
+                "min": { "value": 1735689600000.0, "value_as_string": "2025-01-01T00:00:00.000Z" },
+                "max": { "value": 1738368000000.0, "value_as_string": "2025-02-01T00:00:00.000Z" },
+                "percentiles": { "values": { "50.0": 1737000000000.0, "50.0_as_string": "2025-01-16T04:00:00.000Z" } }
+            }
+        }))?;
+
+        assert_eq!(
+            serde_json::to_value(field.distribution(response))?,
+            json!({
+                "field": "@timestamp",
+                "type": "date",
+                "documents": 120,
+                "missing": 3,
+                "cardinality": 117,
+                "top_values": [{ "value": "2025-01-01T00:00:00.000Z", "count": 2 }],
+                "other_values_count": 100,
+                "min": "2025-01-01T00:00:00.000Z",
+                "max": "2025-02-01T00:00:00.000Z",
+                "percentiles": { "50.0": "2025-01-16T04:00:00.000Z" }
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn filter_by_type() -> anyhow::Result<()> {
+        let fields = response()?.into_fields(&["keyword".to_string()]);
+
+        let names = fields.iter().map(|f| f.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["host.name", "status"]);
+        Ok(())
+    }
+}
@@ -15,9 +15,24 @@
 // specific language governing permissions and limitations
 // under the License.
 
+mod async_esql;
+mod async_search;
 mod base_tools;
+mod cluster_tools;
+mod custom_tools;
+mod document_tools;
+mod errors;
+mod esql_format;
+mod field_tools;
+mod paginated_search;
+mod prompts;
+mod resources;
+mod response_limits;
+mod validation_tools;
+mod write_tools;
 
 use crate::servers::IncludeExclude;
+use crate::servers::elasticsearch::errors::EsError;
 use crate::utils::none_if_empty_string;
 use elasticsearch::Elasticsearch;
 use elasticsearch::auth::Credentials;
@@ -29,10 +44,10 @@ use http::request::Parts;
 use http::{HeaderValue, header};
 use indexmap::IndexMap;
 use rmcp::RoleServer;
-use rmcp::model::ToolAnnotations;
-use rmcp::service::RequestContext;
+use rmcp::model::{ProgressNotificationParam, ProgressToken, ToolAnnotations};
+use rmcp::service::{Peer, RequestContext};
 use serde::de::DeserializeOwned;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use serde_aux::field_attributes::deserialize_bool_from_anything;
 use std::borrow::Cow;
 use std::collections::HashMap;
@@ -58,16 +73,46 @@ pub struct ElasticsearchMcpConfig {
     #[serde(default, deserialize_with = "deserialize_bool_from_anything")]
     pub ssl_skip_verify: bool,
 
+    /// Read-only or read-write mode. Tools that modify data are only available in read-write mode.
+    #[serde(default)]
+    pub mode: Mode,
+
+    /// Size limits of tool responses
+    #[serde(default)]
+    pub response_limits: ResponseLimits,
+
     /// Search templates to expose as tools or resources
     #[serde(default)]
     pub tools: Tools,
 
-    /// Prompts
-    #[serde(default)]
-    pub prompts: Vec<String>,
+    /// Prompts, by name
+    #[serde(default, deserialize_with = "deserialize_prompts")]
+    pub prompts: IndexMap<String, CustomPrompt>,
     // TODO: search as resources?
 }
 
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Mode {
+    #[default]
+    ReadOnly,
+    ReadWrite,
+}
+
+/// Size limits of tool responses, to avoid overflowing the context window of agents. There are no
+/// limits by default.
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct ResponseLimits {
+    /// Maximum size of a tool response, in bytes
+    pub max_bytes: Option<usize>,
+
+    /// Maximum size of a tool response, in estimated tokens (about 4 bytes per token)
+    pub max_tokens: Option<usize>,
+
+    /// Maximum length of strings in JSON results, in characters
+    pub max_string_length: Option<usize>,
+}
+
 // A wrapper around an ES client that provides a client instance configured
 /// for a given request context (i.e. auth credentials)
 #[derive(Clone)]
@@ -108,8 +153,10 @@ impl EsClientProvider {
 
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct Tools {
+    /// Builtin tools to include or exclude. Custom tools are always included.
     #[serde(flatten)]
     pub incl_excl: Option<IncludeExclude>,
+    #[serde(default)]
     pub custom: HashMap<String, CustomTool>,
 }
 
@@ -145,23 +192,35 @@ pub struct EsqlTool {
     format: EsqlResultFormat,
 }
 
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq, schemars::JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum EsqlResultFormat {
+    /// An array of JSON objects, one per row
     #[default]
-    // Output as JSON, either as an array of objects or as a single object.
     Json,
-    // If a single object with a single property, output only its value
+    /// A JSON object with the `columns` and an array of `values` for each row
+    Columnar,
+    /// Comma-separated values, with a header row
+    Csv,
+    /// Tab-separated values, with a header row
+    Tsv,
+    /// A Markdown table
+    Markdown,
+    /// If a single row with a single column, output only its value. Otherwise same as `json`.
     Value,
-    //Csv,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SearchTemplateTool {
     #[serde(flatten)]
     base: ToolBase,
+    /// Indices to search. Required, so that templates can't be run on all indices.
+    index: Vec<String>,
     #[serde(flatten)]
     template: SearchTemplate,
+    /// Return the metadata of each hit (`_id`, `_index`, `_score`, etc.) alongside its `_source`
+    #[serde(default)]
+    include_metadata: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -171,6 +230,50 @@ pub enum SearchTemplate {
     Template(serde_json::Value), // or constrain to an object?
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CustomPrompt {
+    pub description: Option<String>,
+    #[serde(default)]
+    pub arguments: IndexMap<String, PromptArgumentDef>,
+    /// Prompt text, with `{{...}}` placeholders (see the `prompts` module)
+    pub prompt: String,
+}
+
+/// Prompts are a map of named prompts, or a list of prompt texts as in older configurations.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PromptsConfig {
+    Named(IndexMap<String, CustomPrompt>),
+    List(Vec<String>),
+}
+
+/// Read prompts from either form. Prompts of a list are named `prompt-1`, `prompt-2`, etc.
+fn deserialize_prompts<'de, D: Deserializer<'de>>(deserializer: D) -> Result<IndexMap<String, CustomPrompt>, D::Error> {
+    let prompts = match PromptsConfig::deserialize(deserializer)? {
+        PromptsConfig::Named(prompts) => prompts,
+        PromptsConfig::List(texts) => texts
+            .into_iter()
+            .enumerate()
+            .map(|(i, prompt)| {
+                let prompt = CustomPrompt {
+                    description: None,
+                    arguments: IndexMap::new(),
+                    prompt,
+                };
+                (format!("prompt-{}", i + 1), prompt)
+            })
+            .collect(),
+    };
+    Ok(prompts)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PromptArgumentDef {
+    pub description: Option<String>,
+    #[serde(default)]
+    pub required: bool,
+}
+
 #[derive(Clone)]
 pub struct ElasticsearchMcp {}
 
@@ -208,9 +311,20 @@ impl ElasticsearchMcp {
             HeaderValue::from_str(&format!("elastic-mcp/{}", env!("CARGO_PKG_VERSION")))?,
         );
         let transport = transport.build()?;
-        let es_client = Elasticsearch::new(transport);
+        let es_client = EsClientProvider::new(Elasticsearch::new(transport));
 
-        Ok(base_tools::EsBaseTools::new(es_client))
+        let custom_tools = custom_tools::tool_router(&es_client, config.tools.custom)?;
+
+        prompts::check(&config.prompts)?;
+
+        Ok(base_tools::EsBaseTools::new(
+            es_client,
+            config.mode,
+            config.response_limits,
+            config.tools.incl_excl.as_ref(),
+            custom_tools,
+            config.prompts,
+        ))
     }
 }
 
@@ -241,26 +355,67 @@ fn rewrite_localhost(url: &mut Url) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Sends progress notifications for a request, if the client asked for them with a progress token
+pub struct ProgressNotifier {
+    peer: Peer<RoleServer>,
+    token: Option<ProgressToken>,
+}
+
+impl ProgressNotifier {
+    pub fn new(context: &RequestContext<RoleServer>) -> Self {
+        ProgressNotifier {
+            peer: context.peer.clone(),
+            token: context.meta.get_progress_token(),
+        }
+    }
+
+    pub async fn notify(&self, progress: u32, total: Option<u32>, message: String) {
+        let Some(token) = &self.token else {
+            return;
+        };
+
+        let param = ProgressNotificationParam {
+            progress_token: token.clone(),
+            progress,
+            total,
+            message: Some(message),
+        };
+        // Progress is informational: failing to send it shouldn't fail the request
+        if let Err(err) = self.peer.notify_progress(param).await {
+            tracing::warn!("Failed to send progress notification: {err}");
+        }
+    }
+}
+
 /// Map any error to an internal error of the MCP server
 pub fn internal_error(e: impl std::error::Error) -> rmcp::Error {
     rmcp::Error::internal_error(e.to_string(), None)
 }
 
-/// Return an error as an error response to the client, which may be able to take
-/// action to correct it. This should be refined to handle common error types such
-/// as index not found, which could be caused by the client hallucinating an index name.
+/// Check the response status, and return errors as an error response to the client, which may be
+/// able to take action to correct them, e.g. if it hallucinated an index name.
 ///
-/// TODO (in rmcp): if rmcp::Error had a variant that accepts a CallToolResult, this would
-/// allow to use the '?' operator while sending a result to the client.
-pub fn handle_error(result: Result<Response, elasticsearch::Error>) -> Result<Response, rmcp::Error> {
+/// Error responses are parsed into an `EsError`, that `call_tool` converts into a tool error result.
+/// rmcp::Error has no variant that accepts a CallToolResult, so this allows using the '?' operator
+/// in tools while sending a result to the client.
+pub async fn handle_error(result: Result<Response, elasticsearch::Error>) -> Result<Response, rmcp::Error> {
     match result {
-        Ok(resp) => resp.error_for_status_code(),
+        Ok(resp) if resp.status_code().is_client_error() || resp.status_code().is_server_error() => {
+            let status = resp.status_code();
+            let body = resp.text().await.map_err(internal_error)?;
+            tracing::debug!("Error response {status}: {body}");
+            Err(EsError::from_response(status, &body).into_rmcp())
+        }
+        Ok(resp) => Ok(resp),
         Err(e) => {
             tracing::error!("Error: {:?}", &e);
-            Err(e)
+            if e.is_timeout() {
+                Err(EsError::timeout(e.to_string()).into_rmcp())
+            } else {
+                Err(internal_error(e))
+            }
         }
     }
-    .map_err(internal_error)
 }
 
 pub async fn read_json<T: DeserializeOwned>(
@@ -270,12 +425,12 @@ pub async fn read_json<T: DeserializeOwned>(
     // tracing::debug!("Received json {text}");
     // serde_json::from_str(&text).map_err(internal_error)
 
-    let response = handle_error(response)?;
+    let response = handle_error(response).await?;
     response.json().await.map_err(internal_error)
 }
 
 #[allow(dead_code)]
 pub async fn read_text(result: Result<Response, elasticsearch::Error>) -> Result<String, rmcp::Error> {
-    let response = handle_error(result)?;
+    let response = handle_error(result).await?;
     response.text().await.map_err(internal_error)
 }
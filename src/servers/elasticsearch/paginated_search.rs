@@ -0,0 +1,491 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Paginated search, using a point in time (PIT) and `search_after`.
+//!
+//! The first page opens a PIT and returns an opaque cursor that contains the PIT id, the search
+//! request and the sort values of the last hit. The server keeps no state between pages, and the
+//! PIT is closed once all results have been returned.
+//!
+//! Pages are fitted to the response size limits before the cursor is built: hits that don't fit
+//! are left out of the page and returned by the next one, instead of being dropped. Pages are
+//! measured with their cursor, and aren't truncated again afterwards.
+
+use crate::servers::elasticsearch::base_tools::{EsBaseTools, SearchResult, add_source_fields};
+use crate::servers::elasticsearch::response_limits::{contents_size, json_size, truncate_strings};
+use crate::servers::elasticsearch::{ResponseLimits, handle_error, internal_error, read_json};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use elasticsearch::http::StatusCode;
+use elasticsearch::{Elasticsearch, OpenPointInTimeParts, SearchParts};
+use rmcp::RoleServer;
+use rmcp::handler::server::tool::Parameters;
+use rmcp::model::{CallToolResult, Content};
+use rmcp::service::RequestContext;
+use rmcp_macros::{tool, tool_router};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+
+/// How long the PIT is kept between pages, if not specified
+const DEFAULT_KEEP_ALIVE: &str = "5m";
+
+/// Elasticsearch's default page size
+const DEFAULT_PAGE_SIZE: u64 = 10;
+
+/// Tools whose results are fitted to the response size limits when they're built, and must not be
+/// truncated again: dropping hits or cutting the cursor would skip results.
+pub(super) const FITTED_TOOLS: &[&str] = &["search_paginated", "search_next"];
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct PaginatedSearchParams {
+    /// Name of the Elasticsearch index to search
+    index: String,
+
+    /// Name of the fields that need to be returned (optional)
+    fields: Option<Vec<String>>,
+
+    /// Elasticsearch query DSL object that can include query, size (the page size), sort, etc.
+    /// If there is no sort, hits are returned in index order.
+    query_body: Map<String, Value>,
+
+    /// How long results are kept between pages, e.g. `5m` (optional)
+    keep_alive: Option<String>,
+
+    /// Return the metadata of each hit (`_id`, `_index`, `_score`, `highlight`, `fields`,
+    /// `inner_hits` and `sort`) alongside its `_source` (optional, defaults to false)
+    include_metadata: Option<bool>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct SearchNextParams {
+    /// Cursor returned by the previous page
+    cursor: String,
+}
+
+#[tool_router(router = paginated_search_tool_router, vis = "pub(super)")]
+impl EsBaseTools {
+    //---------------------------------------------------------------------------------------------
+    /// Tool: paginated search
+    #[tool(
+        description = "Perform an Elasticsearch search with the provided query DSL, returning the first page of results and a cursor to get the next pages with search_next. Use it to go through large result sets.",
+        annotations(title = "Elasticsearch paginated search", read_only_hint = true)
+    )]
+    async fn search_paginated(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(PaginatedSearchParams {
+            index,
+            fields,
+            query_body,
+            keep_alive,
+            include_metadata,
+        }): Parameters<PaginatedSearchParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let mut body = query_body;
+        add_source_fields(&mut body, fields);
+        // Pages are selected with `search_after`
+        body.remove("from");
+        if !body.contains_key("sort") {
+            body.insert("sort".to_string(), json!(["_shard_doc"]));
+        }
+
+        let keep_alive = keep_alive.unwrap_or(DEFAULT_KEEP_ALIVE.to_string());
+        let response = es_client
+            .open_point_in_time(OpenPointInTimeParts::Index(&[&index]))
+            .keep_alive(&keep_alive)
+            .send()
+            .await;
+        let response: OpenPitResponse = read_json(response).await?;
+
+        let cursor = Cursor {
+            pit_id: response.id,
+            keep_alive,
+            body,
+            search_after: Vec::new(),
+            include_metadata: include_metadata.unwrap_or_default(),
+        };
+
+        // If the first page fails, no cursor is returned to continue the search: close the PIT
+        // instead of keeping it until it expires.
+        let pit_id = cursor.pit_id.clone();
+        let result = search_page(&es_client, &self.response_limits, cursor).await;
+        if !matches!(&result, Ok(result) if result.is_error != Some(true)) {
+            close_pit(&es_client, pit_id).await;
+        }
+        result
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: next page of a paginated search
+    #[tool(
+        description = "Get the next page of results of a paginated search, using the cursor returned by search_paginated or a previous call to search_next.",
+        annotations(title = "Elasticsearch paginated search next page", read_only_hint = true)
+    )]
+    async fn search_next(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(SearchNextParams { cursor }): Parameters<SearchNextParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+        let cursor = Cursor::decode(&cursor)?;
+
+        search_page(&es_client, &self.response_limits, cursor).await
+    }
+}
+
+async fn search_page(
+    es_client: &Elasticsearch,
+    limits: &ResponseLimits,
+    cursor: Cursor,
+) -> Result<CallToolResult, rmcp::Error> {
+    let response = es_client
+        .search(SearchParts::None)
+        .body(cursor.request_body())
+        .send()
+        .await;
+
+    // An expired PIT is a 404 error
+    if let Ok(response) = &response
+        && response.status_code() == StatusCode::NOT_FOUND
+    {
+        return Ok(CallToolResult::error(vec![Content::text(
+            "The search cursor has expired. Start a new paginated search.",
+        )]));
+    }
+
+    let response: SearchResult = read_json(response).await?;
+
+    let page_size = cursor
+        .body
+        .get("size")
+        .and_then(Value::as_u64)
+        .unwrap_or(DEFAULT_PAGE_SIZE);
+    let full_page = response.hits.hits.len() as u64 >= page_size;
+
+    let page = fit_page(response, limits, cursor, full_page)?;
+    if let Some(pit_id) = page.last_pit_id {
+        close_pit(es_client, pit_id).await;
+    }
+
+    Ok(CallToolResult::success(page.contents))
+}
+
+/// The contents of a page of results
+struct Page {
+    contents: Vec<Content>,
+    /// The PIT id, to close it, if this is the last page
+    last_pit_id: Option<String>,
+}
+
+/// Build a page that fits in the response size limits. Hits at the end of the page that don't fit
+/// are left out, and the cursor starts after the last hit returned.
+///
+/// The size of the page is measured on its contents, including the cursor. Paginated search
+/// results are therefore not truncated again once the cursor is built (see [`FITTED_TOOLS`]).
+fn fit_page(
+    mut response: SearchResult,
+    limits: &ResponseLimits,
+    cursor: Cursor,
+    full_page: bool,
+) -> Result<Page, rmcp::Error> {
+    let (hit_strings, agg_strings) = match limits.max_string_length {
+        Some(max_length) => shorten_strings(&mut response, max_length),
+        None => (Vec::new(), 0),
+    };
+    // Number of strings shortened in a page with the first `kept` hits
+    let shortened = |kept: usize| agg_strings + hit_strings.iter().take(kept).sum::<usize>();
+
+    let count = response.hits.hits.len();
+    let mut page = page_contents(response.clone(), &cursor, full_page, count, shortened(count))?;
+    let Some(max_bytes) = limits.max_bytes() else {
+        return Ok(page);
+    };
+
+    let size = contents_size(&page.contents);
+    if size <= max_bytes {
+        return Ok(page);
+    }
+
+    // Estimate the number of hits that fit from the size of the other contents, and then check it
+    // on the actual page, as the cursor and the notes depend on the hits returned.
+    let hit_sizes = response
+        .hits
+        .hits
+        .iter()
+        .map(|hit| {
+            if cursor.include_metadata {
+                json_size(hit)
+            } else {
+                json_size(hit.document())
+            }
+        })
+        .collect::<Vec<_>>();
+    let hits_size = hit_sizes.iter().map(|size| size + 1).sum::<usize>();
+    let mut kept = limits
+        .rows_that_fit(hit_sizes, size.saturating_sub(hits_size))
+        .min(count);
+
+    loop {
+        response.hits.hits.truncate(kept);
+        page = page_contents(response.clone(), &cursor, full_page, count, shortened(kept))?;
+        // At least one hit is returned, so that the search makes progress
+        if kept <= 1 || contents_size(&page.contents) <= max_bytes {
+            return Ok(page);
+        }
+        kept -= 1;
+    }
+}
+
+/// Build the contents of a page from the search response. `count` is the number of hits returned
+/// by Elasticsearch, and `shortened` the number of strings shortened in the page.
+fn page_contents(
+    response: SearchResult,
+    cursor: &Cursor,
+    full_page: bool,
+    count: usize,
+    shortened: usize,
+) -> Result<Page, rmcp::Error> {
+    let left_out = count - response.hits.hits.len();
+    let last_sort = response.hits.hits.last().and_then(|hit| hit.sort.clone());
+    // The PIT id may change between requests
+    let pit_id = response.pit_id.clone().unwrap_or(cursor.pit_id.clone());
+
+    let mut contents = response.into_contents(cursor.include_metadata)?;
+    if shortened > 0 {
+        contents.push(Content::text(format!(
+            "{shortened} long strings were shortened to fit the response size limit."
+        )));
+    }
+
+    match last_sort {
+        Some(search_after) if full_page || left_out > 0 => {
+            let next = Cursor {
+                pit_id,
+                search_after,
+                ..cursor.clone()
+            };
+            if left_out > 0 {
+                contents.push(Content::text(format!(
+                    "{left_out} hits of this page were left out to fit the response size limit. \
+                     They are returned by the next page."
+                )));
+            }
+            contents.push(Content::text(format!(
+                "More results are available. Call search_next with cursor: {}",
+                next.encode()?
+            )));
+            Ok(Page {
+                contents,
+                last_pit_id: None,
+            })
+        }
+        _ => {
+            contents.push(Content::text("No more results."));
+            Ok(Page {
+                contents,
+                last_pit_id: Some(pit_id),
+            })
+        }
+    }
+}
+
+/// Shorten long strings in the hits and aggregations of a response, and return the number of strings
+/// shortened in each hit and in the aggregations.
+fn shorten_strings(response: &mut SearchResult, max_length: usize) -> (Vec<usize>, usize) {
+    let in_hits = response
+        .hits
+        .hits
+        .iter_mut()
+        .map(|hit| {
+            [
+                &mut hit.source,
+                &mut hit.fields,
+                &mut hit.highlight,
+                &mut hit.inner_hits,
+            ]
+            .into_iter()
+            .flatten()
+            .map(|value| truncate_strings(value, max_length))
+            .sum()
+        })
+        .collect::<Vec<usize>>();
+
+    let in_aggs = response
+        .aggregations
+        .values_mut()
+        .map(|value| truncate_strings(value, max_length))
+        .sum::<usize>();
+    (in_hits, in_aggs)
+}
+
+async fn close_pit(es_client: &Elasticsearch, pit_id: String) {
+    let response = es_client
+        .close_point_in_time()
+        .body(json!({ "id": pit_id }))
+        .send()
+        .await;
+
+    // Not an error for the client: the PIT will expire anyway
+    if let Err(err) = handle_error(response).await {
+        tracing::warn!("Failed to close point in time: {err}");
+    }
+}
+
+/// State of a paginated search, passed to the client as an opaque string
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Cursor {
+    pit_id: String,
+    keep_alive: String,
+    /// Search request, without the PIT and `search_after`
+    body: Map<String, Value>,
+    /// Sort values of the last hit of the previous page
+    search_after: Vec<Value>,
+    /// Return hits with their metadata
+    #[serde(default)]
+    include_metadata: bool,
+}
+
+impl Cursor {
+    fn encode(&self) -> Result<String, rmcp::Error> {
+        let json = serde_json::to_vec(self).map_err(internal_error)?;
+        Ok(URL_SAFE_NO_PAD.encode(json))
+    }
+
+    fn decode(cursor: &str) -> Result<Cursor, rmcp::Error> {
+        URL_SAFE_NO_PAD
+            .decode(cursor.trim())
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .ok_or_else(|| rmcp::Error::invalid_params("invalid search cursor", None))
+    }
+
+    fn request_body(&self) -> Map<String, Value> {
+        let mut body = self.body.clone();
+        body.insert(
+            "pit".to_string(),
+            json!({ "id": self.pit_id, "keep_alive": self.keep_alive }),
+        );
+        if !self.search_after.is_empty() {
+            body.insert("search_after".to_string(), json!(self.search_after));
+        }
+        body
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Type definitions for ES responses
+
+#[derive(Serialize, Deserialize)]
+pub struct OpenPitResponse {
+    pub id: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_roundtrip() -> anyhow::Result<()> {
+        let cursor = Cursor {
+            pit_id: "46ToAwMDaWR5".to_string(),
+            keep_alive: "5m".to_string(),
+            body: serde_json::from_str(r#"{ "size": 100, "sort": [{ "@timestamp": "asc" }] }"#)?,
+            search_after: vec![json!(1718000000000_u64), json!(42)],
+            include_metadata: true,
+        };
+
+        let encoded = cursor.encode()?;
+        assert_eq!(Cursor::decode(&encoded)?, cursor);
+        assert!(Cursor::decode("not a cursor").is_err());
+
+        assert_eq!(
+            Value::Object(cursor.request_body()),
+            json!({
+                "size": 100,
+                "sort": [{ "@timestamp": "asc" }],
+                "pit": { "id": "46ToAwMDaWR5", "keep_alive": "5m" },
+                "search_after": [1718000000000_u64, 42]
+            })
+        );
+        Ok(())
+    }
+
+    /// Number of hits and cursor of a page
+    fn read_page(page: &Page) -> anyhow::Result<(usize, Option<Cursor>)> {
+        let text = |i: usize| page.contents[i].as_text().map(|t| t.text.as_str()).unwrap_or_default();
+        let hits: Vec<Value> = serde_json::from_str(text(1))?;
+        let cursor = text(page.contents.len() - 1)
+            .strip_prefix("More results are available. Call search_next with cursor: ")
+            .map(Cursor::decode)
+            .transpose()?;
+        Ok((hits.len(), cursor))
+    }
+
+    #[test]
+    fn fit_page_to_limits() -> anyhow::Result<()> {
+        let hits = (0..10)
+            .map(|i| json!({ "_id": i.to_string(), "_source": { "message": "x".repeat(100) }, "sort": [i] }))
+            .collect::<Vec<_>>();
+        let response: SearchResult = serde_json::from_value(json!({ "hits": { "hits": hits } }))?;
+        let cursor = Cursor {
+            pit_id: "46ToAwMDaWR5".to_string(),
+            keep_alive: "5m".to_string(),
+            body: Map::new(),
+            search_after: Vec::new(),
+            include_metadata: false,
+        };
+        let fit = |max_bytes: Option<usize>| {
+            let limits = ResponseLimits {
+                max_bytes,
+                ..Default::default()
+            };
+            let page = fit_page(response.clone(), &limits, cursor.clone(), true)?;
+            let size = contents_size(&page.contents);
+            anyhow::Ok((page, size))
+        };
+
+        let (page, full_size) = fit(None)?;
+        let (hits, next) = read_page(&page)?;
+        assert_eq!(hits, 10);
+        assert_eq!(next.map(|c| c.search_after), Some(vec![json!(9)]));
+
+        // Just above the size of the full page
+        let (page, size) = fit(Some(full_size + 1))?;
+        assert_eq!(size, full_size);
+        assert_eq!(read_page(&page)?.0, 10);
+
+        // Just below: the page includes a note and a cursor to the hits left out
+        let limit = full_size - 1;
+        let (page, size) = fit(Some(limit))?;
+        assert!(size <= limit);
+        assert!(
+            page.contents
+                .iter()
+                .any(|c| c.as_text().is_some_and(|t| t.text.contains("left out")))
+        );
+        let (hits, next) = read_page(&page)?;
+        assert!(hits < 10);
+        assert_eq!(next.map(|c| c.search_after), Some(vec![json!(hits - 1)]));
+
+        // At least one hit is returned
+        let (page, _) = fit(Some(10))?;
+        assert_eq!(read_page(&page)?.0, 1);
+        Ok(())
+    }
+}
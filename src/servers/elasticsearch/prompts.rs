@@ -0,0 +1,270 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Prompts defined in the configuration file.
+//!
+//! A prompt's text can contain placeholders:
+//! - `{{name}}` is replaced with the value of the `name` argument,
+//! - `{{mappings:target}}` is replaced with the merged mappings of matching indices,
+//! - `{{indices:target}}` is replaced with the list of indices matching a pattern.
+//!
+//! For cluster data placeholders, `target` is the name of an argument, or a literal index name or
+//! pattern if there is no such argument. If the argument is optional and not provided, rendering
+//! the prompt fails rather than fetching data for an index named like the argument.
+
+use crate::servers::elasticsearch::base_tools::{MappingResponse, cat_indices, merge_mappings};
+use crate::servers::elasticsearch::{CustomPrompt, internal_error, read_json};
+use elasticsearch::Elasticsearch;
+use elasticsearch::indices::IndicesGetMappingParts;
+use indexmap::IndexMap;
+use rmcp::model::{GetPromptResult, JsonObject, Prompt, PromptArgument, PromptMessage, PromptMessageRole};
+use serde_json::Value;
+
+const OPEN: &str = "{{";
+const CLOSE: &str = "}}";
+
+/// A parsed prompt template segment
+#[derive(Debug, PartialEq)]
+enum Segment<'a> {
+    Text(&'a str),
+    Argument(&'a str),
+    Data { source: DataSource, target: &'a str },
+}
+
+/// Cluster data that can be injected in a prompt
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum DataSource {
+    Mappings,
+    Indices,
+}
+
+fn parse(template: &str) -> anyhow::Result<Vec<Segment<'_>>> {
+    let mut result = Vec::new();
+    let mut rest = template;
+
+    while let Some(pos) = rest.find(OPEN) {
+        if pos > 0 {
+            result.push(Segment::Text(&rest[..pos]));
+        }
+        rest = &rest[pos + OPEN.len()..];
+
+        let Some(end) = rest.find(CLOSE) else {
+            anyhow::bail!("missing closing braces in prompt template");
+        };
+        let expr = rest[..end].trim();
+        rest = &rest[end + CLOSE.len()..];
+
+        let segment = match expr.split_once(':') {
+            None => Segment::Argument(expr),
+            Some((source, target)) => {
+                let source = match source.trim() {
+                    "mappings" => DataSource::Mappings,
+                    "indices" => DataSource::Indices,
+                    other => anyhow::bail!("unknown prompt placeholder '{other}'"),
+                };
+                Segment::Data {
+                    source,
+                    target: target.trim(),
+                }
+            }
+        };
+        result.push(segment);
+    }
+
+    if !rest.is_empty() {
+        result.push(Segment::Text(rest));
+    }
+
+    Ok(result)
+}
+
+/// Check the prompts configuration, so that errors are detected at startup.
+pub fn check(prompts: &IndexMap<String, CustomPrompt>) -> anyhow::Result<()> {
+    for (name, prompt) in prompts {
+        for segment in parse(&prompt.prompt).map_err(|e| anyhow::anyhow!("prompt '{name}': {e}"))? {
+            if let Segment::Argument(arg) = segment
+                && !prompt.arguments.contains_key(arg)
+            {
+                anyhow::bail!("prompt '{name}': unknown argument '{arg}'");
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Prompt definitions, for `prompts/list`
+pub fn list(prompts: &IndexMap<String, CustomPrompt>) -> Vec<Prompt> {
+    prompts
+        .iter()
+        .map(|(name, prompt)| {
+            let arguments = prompt
+                .arguments
+                .iter()
+                .map(|(arg_name, arg)| PromptArgument {
+                    name: arg_name.clone(),
+                    description: arg.description.clone(),
+                    required: Some(arg.required),
+                })
+                .collect::<Vec<_>>();
+
+            Prompt::new(name, prompt.description.clone(), Some(arguments))
+        })
+        .collect()
+}
+
+/// Render a prompt, for `prompts/get`
+pub async fn get(
+    es_client: &Elasticsearch,
+    prompt: &CustomPrompt,
+    arguments: Option<JsonObject>,
+) -> Result<GetPromptResult, rmcp::Error> {
+    let arguments = arguments.unwrap_or_default();
+
+    // Check required arguments
+    for (name, arg) in &prompt.arguments {
+        if arg.required && !arguments.contains_key(name) {
+            return Err(rmcp::Error::invalid_params(format!("missing argument '{name}'"), None));
+        }
+    }
+
+    let segments = parse(&prompt.prompt).map_err(|e| rmcp::Error::internal_error(e.to_string(), None))?;
+
+    let mut text = String::new();
+    for segment in segments {
+        match segment {
+            Segment::Text(s) => text.push_str(s),
+            Segment::Argument(name) => text.push_str(&argument_value(&arguments, name).unwrap_or_default()),
+            Segment::Data { source, target } => {
+                let target = data_target(prompt, &arguments, target)?;
+                text.push_str(&fetch_data(es_client, source, &target).await?);
+            }
+        }
+    }
+
+    Ok(GetPromptResult {
+        description: prompt.description.clone(),
+        messages: vec![PromptMessage::new_text(PromptMessageRole::User, text)],
+    })
+}
+
+fn argument_value(arguments: &JsonObject, name: &str) -> Option<String> {
+    match arguments.get(name)? {
+        Value::String(s) => Some(s.clone()),
+        Value::Null => None,
+        value => Some(value.to_string()),
+    }
+}
+
+/// The index name or pattern of a cluster data placeholder: the value of the argument named
+/// `target`, or `target` itself if there's no such argument.
+fn data_target(prompt: &CustomPrompt, arguments: &JsonObject, target: &str) -> Result<String, rmcp::Error> {
+    if !prompt.arguments.contains_key(target) {
+        return Ok(target.to_string());
+    }
+    argument_value(arguments, target).ok_or_else(|| {
+        rmcp::Error::invalid_params(
+            format!("missing argument '{target}', needed to fetch cluster data for the prompt"),
+            None,
+        )
+    })
+}
+
+async fn fetch_data(es_client: &Elasticsearch, source: DataSource, target: &str) -> Result<String, rmcp::Error> {
+    let json = match source {
+        DataSource::Mappings => {
+            let response = es_client
+                .indices()
+                .get_mapping(IndicesGetMappingParts::Index(&[target]))
+                .send()
+                .await;
+            let response: MappingResponse = read_json(response).await?;
+            serde_json::to_string(&merge_mappings(response))
+        }
+        DataSource::Indices => {
+            let response = cat_indices(es_client, target).await?;
+            serde_json::to_string(&response)
+        }
+    };
+
+    json.map_err(internal_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::servers::elasticsearch::ElasticsearchMcpConfig;
+    use serde_json::json;
+
+    #[test]
+    fn parse_template() -> anyhow::Result<()> {
+        assert_eq!(
+            parse("Look at {{ index }}: {{mappings:index}}.")?,
+            vec![
+                Segment::Text("Look at "),
+                Segment::Argument("index"),
+                Segment::Text(": "),
+                Segment::Data {
+                    source: DataSource::Mappings,
+                    target: "index"
+                },
+                Segment::Text("."),
+            ]
+        );
+
+        assert!(parse("{{index").is_err());
+        assert!(parse("{{foo:index}}").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn data_target_argument() -> anyhow::Result<()> {
+        let prompt: CustomPrompt = serde_json::from_value(json!({
+            "arguments": { "index": { "required": false } },
+            "prompt": "{{mappings:index}} {{indices:logs-*}}",
+        }))?;
+
+        let arguments = json!({ "index": "my-index" });
+        let Value::Object(arguments) = arguments else {
+            unreachable!()
+        };
+        assert_eq!(data_target(&prompt, &arguments, "index")?, "my-index");
+        assert_eq!(data_target(&prompt, &arguments, "logs-*")?, "logs-*");
+
+        // A missing optional argument isn't used as a literal index name
+        assert!(data_target(&prompt, &JsonObject::new(), "index").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn prompts_config() -> anyhow::Result<()> {
+        let config: ElasticsearchMcpConfig = serde_json::from_value(json!({
+            "url": "http://localhost:9200",
+            "prompts": { "investigate": { "prompt": "Investigate the cluster" } },
+        }))?;
+        assert_eq!(config.prompts["investigate"].prompt, "Investigate the cluster");
+
+        // List of prompt texts, from older configurations
+        let config: ElasticsearchMcpConfig = serde_json::from_value(json!({
+            "url": "http://localhost:9200",
+            "prompts": ["Investigate the cluster", "List the indices"],
+        }))?;
+        assert_eq!(config.prompts.keys().collect::<Vec<_>>(), vec!["prompt-1", "prompt-2"]);
+        assert_eq!(config.prompts["prompt-2"].prompt, "List the indices");
+        assert!(config.prompts["prompt-2"].arguments.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,185 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Indices exposed as MCP resources, with URIs like `elasticsearch://index/{name}/mapping`.
+
+use crate::servers::elasticsearch::base_tools::{Hit, MappingResponse, SearchResult, cat_indices};
+use crate::servers::elasticsearch::{internal_error, read_json};
+use elasticsearch::indices::{IndicesGetMappingParts, IndicesGetSettingsParts};
+use elasticsearch::{Elasticsearch, SearchParts};
+use rmcp::model::{
+    AnnotateAble, RawResource, RawResourceTemplate, ReadResourceResult, Resource, ResourceContents, ResourceTemplate,
+};
+use serde::Serialize;
+use serde_json::{Value, json};
+
+const INDEX_PREFIX: &str = "elasticsearch://index/";
+const JSON_MIME_TYPE: &str = "application/json";
+
+/// Number of documents returned by the `sample` resource
+const SAMPLE_SIZE: usize = 5;
+
+/// Information about an index that can be read as a resource
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum IndexResource {
+    Mapping,
+    Settings,
+    Sample,
+}
+
+impl IndexResource {
+    const ALL: [IndexResource; 3] = [IndexResource::Mapping, IndexResource::Settings, IndexResource::Sample];
+
+    fn name(&self) -> &'static str {
+        match self {
+            IndexResource::Mapping => "mapping",
+            IndexResource::Settings => "settings",
+            IndexResource::Sample => "sample",
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self {
+            IndexResource::Mapping => "Field mappings of an Elasticsearch index",
+            IndexResource::Settings => "Settings of an Elasticsearch index",
+            IndexResource::Sample => "A few sample documents from an Elasticsearch index",
+        }
+    }
+
+    fn uri(&self, index: &str) -> String {
+        format!("{INDEX_PREFIX}{index}/{}", self.name())
+    }
+}
+
+/// Parse a resource URI into an index name and the resource kind
+fn parse_uri(uri: &str) -> Option<(&str, IndexResource)> {
+    let (index, name) = uri.strip_prefix(INDEX_PREFIX)?.rsplit_once('/')?;
+    if index.is_empty() {
+        return None;
+    }
+    let resource = IndexResource::ALL.into_iter().find(|r| r.name() == name)?;
+    Some((index, resource))
+}
+
+/// Resource templates, for `resources/templates/list`
+pub fn templates() -> Vec<ResourceTemplate> {
+    IndexResource::ALL
+        .iter()
+        .map(|r| {
+            RawResourceTemplate {
+                uri_template: r.uri("{name}"),
+                name: format!("index {}", r.name()),
+                description: Some(r.description().to_string()),
+                mime_type: Some(JSON_MIME_TYPE.to_string()),
+            }
+            .no_annotation()
+        })
+        .collect()
+}
+
+/// Index mappings, for `resources/list`
+pub async fn list(es_client: &Elasticsearch) -> Result<Vec<Resource>, rmcp::Error> {
+    let indices = cat_indices(es_client, "*").await?;
+
+    let resources = indices
+        .into_iter()
+        .map(|index| {
+            let mut resource = RawResource::new(IndexResource::Mapping.uri(&index.index), index.index.clone());
+            resource.description = Some(format!(
+                "Mappings of index {} ({} documents)",
+                index.index, index.doc_count
+            ));
+            resource.mime_type = Some(JSON_MIME_TYPE.to_string());
+            resource.no_annotation()
+        })
+        .collect();
+
+    Ok(resources)
+}
+
+/// Read a resource, for `resources/read`
+pub async fn read(es_client: &Elasticsearch, uri: String) -> Result<ReadResourceResult, rmcp::Error> {
+    let Some((index, resource)) = parse_uri(&uri) else {
+        return Err(rmcp::Error::resource_not_found(
+            format!("unknown resource '{uri}'"),
+            None,
+        ));
+    };
+
+    let text = match resource {
+        IndexResource::Mapping => {
+            let response = es_client
+                .indices()
+                .get_mapping(IndicesGetMappingParts::Index(&[index]))
+                .send()
+                .await;
+            let response: MappingResponse = read_json(response).await?;
+            to_json(&response)?
+        }
+        IndexResource::Settings => {
+            let response = es_client
+                .indices()
+                .get_settings(IndicesGetSettingsParts::Index(&[index]))
+                .send()
+                .await;
+            let response: Value = read_json(response).await?;
+            to_json(&response)?
+        }
+        IndexResource::Sample => {
+            let response = es_client
+                .search(SearchParts::Index(&[index]))
+                .body(json!({ "size": SAMPLE_SIZE }))
+                .send()
+                .await;
+            let response: SearchResult = read_json(response).await?;
+            let documents = response.hits.hits.iter().map(Hit::document).collect::<Vec<_>>();
+            to_json(&documents)?
+        }
+    };
+
+    Ok(ReadResourceResult {
+        contents: vec![ResourceContents::TextResourceContents {
+            uri,
+            mime_type: Some(JSON_MIME_TYPE.to_string()),
+            text,
+        }],
+    })
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String, rmcp::Error> {
+    serde_json::to_string(value).map_err(internal_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_resource_uri() {
+        assert_eq!(
+            parse_uri("elasticsearch://index/my-index/mapping"),
+            Some(("my-index", IndexResource::Mapping))
+        );
+        assert_eq!(
+            parse_uri("elasticsearch://index/my-index/sample"),
+            Some(("my-index", IndexResource::Sample))
+        );
+        assert_eq!(parse_uri("elasticsearch://index/my-index/foo"), None);
+        assert_eq!(parse_uri("elasticsearch://index//mapping"), None);
+        assert_eq!(parse_uri("file:///my-index/mapping"), None);
+    }
+}
@@ -0,0 +1,481 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Truncation of tool responses that exceed the configured size limits.
+//!
+//! Long strings in JSON contents are shortened first. If the response is still too large, rows are
+//! dropped from its largest contents: elements of their largest array for JSON contents, and lines
+//! for text contents like CSV. Contents that have no rows left to drop, like a single large JSON
+//! object, are cut short. A note is then added with what was left out.
+//!
+//! Contents that aren't truncated are returned as is, and only truncated JSON contents are
+//! serialized again.
+
+use crate::servers::elasticsearch::ResponseLimits;
+use rmcp::model::{CallToolResult, Content, RawContent};
+use serde_json::Value;
+
+/// Rough estimate of the size of a token
+const BYTES_PER_TOKEN: usize = 4;
+
+/// Room left in the size budget for the truncation note
+const NOTE_SIZE: usize = 300;
+
+/// Room for the marker at the end of contents that are cut short
+const CUT_MARKER_SIZE: usize = 40;
+
+impl ResponseLimits {
+    /// Maximum response size in bytes, if any
+    pub fn max_bytes(&self) -> Option<usize> {
+        let from_tokens = self.max_tokens.map(|tokens| tokens * BYTES_PER_TOKEN);
+        match (self.max_bytes, from_tokens) {
+            (Some(bytes), Some(tokens)) => Some(bytes.min(tokens)),
+            (bytes, tokens) => bytes.or(tokens),
+        }
+    }
+
+    /// Number of leading rows that fit in the size limit, for tools that need to know which rows
+    /// are returned, e.g. to continue after the last one. `reserved` is the size of the other
+    /// contents of the response. At least one row is kept, so that callers make progress.
+    pub fn rows_that_fit(&self, row_sizes: impl IntoIterator<Item = usize>, reserved: usize) -> usize {
+        let Some(max_bytes) = self.max_bytes() else {
+            return usize::MAX;
+        };
+        let budget = max_bytes.saturating_sub(reserved);
+
+        let mut total = 0;
+        let mut count = 0;
+        for size in row_sizes {
+            // Include the separating comma
+            total += size + 1;
+            if total > budget && count > 0 {
+                break;
+            }
+            count += 1;
+        }
+        count
+    }
+
+    /// Truncate a tool result so that it fits in the limits.
+    pub fn apply(&self, result: CallToolResult) -> CallToolResult {
+        let max_bytes = self.max_bytes();
+        if max_bytes.is_none() && self.max_string_length.is_none() {
+            return result;
+        }
+
+        let mut parts = result.content.into_iter().map(Part::new).collect::<Vec<_>>();
+
+        let mut shortened = 0;
+        if let Some(max_length) = self.max_string_length {
+            for part in &mut parts {
+                shortened += part.shorten_strings(max_length);
+            }
+        }
+
+        let mut dropped = 0;
+        let mut cut = 0;
+        if let Some(max_bytes) = max_bytes {
+            let budget = max_bytes.saturating_sub(NOTE_SIZE);
+            // Each iteration drops rows or cuts a content, so this terminates
+            loop {
+                let total = parts.iter().map(Part::size).sum::<usize>();
+                if total <= budget {
+                    break;
+                }
+                let excess = total - budget;
+
+                let largest = (0..parts.len())
+                    .filter(|&i| !parts[i].cut && parts[i].size() > 0)
+                    .max_by_key(|&i| parts[i].size());
+                let Some(largest) = largest else {
+                    break;
+                };
+
+                let rows = parts[largest].drop_rows(excess);
+                if rows == 0 {
+                    parts[largest].cut(excess);
+                    cut += 1;
+                }
+                dropped += rows;
+            }
+        }
+
+        let mut content = parts.into_iter().map(Part::into_content).collect::<Vec<_>>();
+        if dropped > 0 || shortened > 0 || cut > 0 {
+            content.push(Content::text(truncation_note(dropped, shortened, cut)));
+        }
+
+        CallToolResult { content, ..result }
+    }
+}
+
+fn truncation_note(dropped: usize, shortened: usize, cut: usize) -> String {
+    let mut details = Vec::new();
+    if dropped > 0 {
+        details.push(format!("{dropped} more rows not shown"));
+    }
+    if shortened > 0 {
+        details.push(format!("{shortened} long strings shortened"));
+    }
+    if cut > 0 {
+        details.push(format!("{cut} contents cut short"));
+    }
+
+    format!(
+        "Results truncated to fit the response size limit: {}. To get complete results, narrow the query \
+         with more specific filters, return fewer fields, or request fewer rows (e.g. with `size` or `LIMIT`).",
+        details.join(", ")
+    )
+}
+
+/// A content of a tool result, with its parsed value if it's JSON
+struct Part {
+    content: Content,
+    json: Option<Value>,
+    /// The JSON value was modified, and must be serialized again
+    changed: bool,
+    /// The content was cut short, and can't be truncated further
+    cut: bool,
+}
+
+impl Part {
+    fn new(content: Content) -> Self {
+        // JSON contents are serialized to text
+        let json = match &content.raw {
+            RawContent::Text(text) if text.text.starts_with(['{', '[']) => serde_json::from_str(&text.text).ok(),
+            _ => None,
+        };
+        Part {
+            content,
+            json,
+            changed: false,
+            cut: false,
+        }
+    }
+
+    fn text_mut(&mut self) -> Option<&mut String> {
+        match &mut self.content.raw {
+            RawContent::Text(text) => Some(&mut text.text),
+            _ => None,
+        }
+    }
+
+    fn size(&self) -> usize {
+        match (&self.json, &self.content.raw) {
+            (Some(value), _) if self.changed => json_size(value),
+            (_, RawContent::Text(text)) => text.text.len(),
+            // Images, resources, etc. can't be truncated
+            _ => 0,
+        }
+    }
+
+    fn shorten_strings(&mut self, max_length: usize) -> usize {
+        let Some(value) = &mut self.json else {
+            return 0;
+        };
+        let shortened = truncate_strings(value, max_length);
+        self.changed |= shortened > 0;
+        shortened
+    }
+
+    /// Drop rows from the end until `excess` bytes are removed, and return the number of rows dropped.
+    fn drop_rows(&mut self, excess: usize) -> usize {
+        if let Some(value) = &mut self.json {
+            let Some(rows) = rows_mut(value) else {
+                return 0;
+            };
+            let mut removed = 0;
+            let mut dropped = 0;
+            while removed < excess
+                && let Some(row) = rows.pop()
+            {
+                // Include the separating comma
+                removed += json_size(&row) + 1;
+                dropped += 1;
+            }
+            self.changed |= dropped > 0;
+            return dropped;
+        }
+
+        let Some(text) = self.text_mut() else {
+            return 0;
+        };
+        // Keep the header of CSV and TSV text, and the header and separator of Markdown tables
+        let header_lines = if text.starts_with('|') { 2 } else { 1 };
+        let mut lines = text.lines().collect::<Vec<_>>();
+        let mut removed = 0;
+        let mut dropped = 0;
+        while removed < excess && lines.len() > header_lines {
+            if let Some(line) = lines.pop() {
+                removed += line.len() + 1;
+                dropped += 1;
+            }
+        }
+        if dropped > 0 {
+            let mut truncated = lines.join("\n");
+            truncated.push('\n');
+            *text = truncated;
+        }
+        dropped
+    }
+
+    /// Cut the text of the content, or of its serialized JSON, so that `excess` bytes are removed.
+    /// JSON contents become text, as their end is missing.
+    fn cut(&mut self, excess: usize) {
+        let serialized = match self.json.take() {
+            Some(value) if self.changed => Some(value.to_string()),
+            _ => None,
+        };
+        let Some(text) = self.text_mut() else {
+            return;
+        };
+        if let Some(serialized) = serialized {
+            *text = serialized;
+        }
+
+        let mut len = text.len().saturating_sub(excess + CUT_MARKER_SIZE);
+        while !text.is_char_boundary(len) {
+            len -= 1;
+        }
+        let more = text.len() - len;
+        text.truncate(len);
+        text.push_str(&format!("... [{more} more bytes]"));
+
+        self.changed = false;
+        self.cut = true;
+    }
+
+    fn into_content(mut self) -> Content {
+        if let Some(value) = self.json.take().filter(|_| self.changed)
+            && let Some(text) = self.text_mut()
+        {
+            *text = value.to_string();
+        }
+        self.content
+    }
+}
+
+/// Size of tool result contents, as measured when truncating them
+pub fn contents_size(contents: &[Content]) -> usize {
+    contents
+        .iter()
+        .map(|content| match &content.raw {
+            RawContent::Text(text) => text.text.len(),
+            _ => 0,
+        })
+        .sum()
+}
+
+pub fn json_size(value: &impl serde::Serialize) -> usize {
+    serde_json::to_string(value).map(|json| json.len()).unwrap_or_default()
+}
+
+/// The rows of a JSON value: the value itself if it's an array, or its largest array property
+/// (e.g. `values` in ES|QL columnar results).
+fn rows_mut(value: &mut Value) -> Option<&mut Vec<Value>> {
+    match value {
+        Value::Array(rows) => Some(rows),
+        Value::Object(obj) => obj
+            .values_mut()
+            .filter_map(|v| match v {
+                Value::Array(rows) => Some(rows),
+                _ => None,
+            })
+            .max_by_key(|rows| rows.iter().map(json_size).sum::<usize>()),
+        _ => None,
+    }
+    .filter(|rows| !rows.is_empty())
+}
+
+/// Shorten strings longer than `max_length` characters, and return the number of strings shortened.
+pub fn truncate_strings(value: &mut Value, max_length: usize) -> usize {
+    match value {
+        Value::String(s) => match s.char_indices().nth(max_length) {
+            Some((idx, _)) => {
+                let more = s[idx..].chars().count();
+                s.truncate(idx);
+                s.push_str(&format!("... [{more} more characters]"));
+                1
+            }
+            None => 0,
+        },
+        Value::Array(values) => values.iter_mut().map(|v| truncate_strings(v, max_length)).sum(),
+        Value::Object(obj) => obj.values_mut().map(|v| truncate_strings(v, max_length)).sum(),
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn text(content: &Content) -> &str {
+        &content.as_text().unwrap().text
+    }
+
+    #[test]
+    fn no_limits() -> anyhow::Result<()> {
+        let result = CallToolResult::success(vec![Content::json(json!([{ "message": "x".repeat(10_000) }]))?]);
+        let limited = ResponseLimits::default().apply(result.clone());
+        assert_eq!(limited, result);
+        Ok(())
+    }
+
+    #[test]
+    fn drop_json_rows() -> anyhow::Result<()> {
+        let rows = (0..100)
+            .map(|i| json!({ "id": i, "message": "x".repeat(100) }))
+            .collect::<Vec<_>>();
+        let result = CallToolResult::success(vec![Content::text("Results"), Content::json(&rows)?]);
+
+        let limits = ResponseLimits {
+            max_tokens: Some(1000),
+            ..Default::default()
+        };
+        let limited = limits.apply(result);
+
+        let size = limited.content.iter().map(|c| text(c).len()).sum::<usize>();
+        assert!(size <= 4000);
+
+        let kept: Vec<Value> = serde_json::from_str(text(&limited.content[1]))?;
+        assert_eq!(kept[..], rows[..kept.len()]);
+        assert!(text(&limited.content[2]).contains(&format!("{} more rows not shown", 100 - kept.len())));
+        Ok(())
+    }
+
+    #[test]
+    fn drop_columnar_and_text_rows() -> anyhow::Result<()> {
+        let limits = ResponseLimits {
+            max_bytes: Some(400),
+            ..Default::default()
+        };
+
+        let values = (0..50).map(|i| json!([i, "some text"])).collect::<Vec<_>>();
+        let result = CallToolResult::success(vec![Content::json(json!({
+            "columns": [{ "name": "id", "type": "long" }, { "name": "text", "type": "text" }],
+            "values": values,
+        }))?]);
+        let limited = limits.apply(result);
+        let kept: Value = serde_json::from_str(text(&limited.content[0]))?;
+        assert_eq!(kept["columns"].as_array().unwrap().len(), 2);
+        assert!(kept["values"].as_array().unwrap().len() < 50);
+
+        let csv = (0..50).fold("id,text\n".to_string(), |csv, i| csv + &format!("{i},some text\n"));
+        let limited = limits.apply(CallToolResult::success(vec![Content::text(csv)]));
+        let kept = text(&limited.content[0]);
+        assert!(kept.starts_with("id,text\n0,some text\n"));
+        assert!(kept.len() <= 100);
+        Ok(())
+    }
+
+    #[test]
+    fn shorten_strings() -> anyhow::Result<()> {
+        let result = CallToolResult::success(vec![Content::json(json!([
+            { "message": "é".repeat(30), "tags": ["short", "a".repeat(25)] }
+        ]))?]);
+
+        let limits = ResponseLimits {
+            max_string_length: Some(20),
+            ..Default::default()
+        };
+        let limited = limits.apply(result);
+
+        let value: Value = serde_json::from_str(text(&limited.content[0]))?;
+        assert_eq!(
+            value,
+            json!([{
+                "message": format!("{}... [10 more characters]", "é".repeat(20)),
+                "tags": ["short", format!("{}... [5 more characters]", "a".repeat(20))]
+            }])
+        );
+        assert!(text(&limited.content[1]).contains("2 long strings shortened"));
+        Ok(())
+    }
+
+    #[test]
+    fn cut_single_object() -> anyhow::Result<()> {
+        let document = json!({ "title": "A document", "body": "x".repeat(2000) });
+        let result = CallToolResult::success(vec![Content::text("Document:"), Content::json(&document)?]);
+
+        let limits = ResponseLimits {
+            max_bytes: Some(1000),
+            ..Default::default()
+        };
+        let limited = limits.apply(result);
+
+        let size = limited.content.iter().map(|c| text(c).len()).sum::<usize>();
+        assert!(size <= 1000);
+        assert_eq!(text(&limited.content[0]), "Document:");
+        assert!(text(&limited.content[1]).starts_with(r#"{"body":"xxx"#));
+        assert!(text(&limited.content[1]).ends_with("more bytes]"));
+        assert!(text(&limited.content[2]).contains("1 contents cut short"));
+        Ok(())
+    }
+
+    #[test]
+    fn truncate_several_contents() -> anyhow::Result<()> {
+        let rows = (0..20).map(|i| json!({ "id": i })).collect::<Vec<_>>();
+        let result = CallToolResult::success(vec![
+            Content::json(&rows)?,
+            Content::text("y".repeat(600)),
+            Content::text("z".repeat(600)),
+        ]);
+
+        let limits = ResponseLimits {
+            max_bytes: Some(800),
+            ..Default::default()
+        };
+        let limited = limits.apply(result);
+
+        let size = limited.content.iter().map(|c| text(c).len()).sum::<usize>();
+        assert!(size <= 800);
+        assert!(text(&limited.content[3]).contains("2 contents cut short"));
+        Ok(())
+    }
+
+    #[test]
+    fn keep_unchanged_contents() -> anyhow::Result<()> {
+        // Key order isn't preserved when JSON is parsed and serialized again
+        let unordered = r#"{"z":1,"a":[1,2,3]}"#;
+        let rows = (0..100).map(|i| json!({ "id": i })).collect::<Vec<_>>();
+        let result = CallToolResult::success(vec![Content::text(unordered), Content::json(&rows)?]);
+
+        let limits = ResponseLimits {
+            max_bytes: Some(600),
+            max_string_length: Some(100),
+            ..Default::default()
+        };
+        let limited = limits.apply(result);
+        assert_eq!(text(&limited.content[0]), unordered);
+        assert!(text(&limited.content[2]).contains("more rows not shown"));
+        Ok(())
+    }
+
+    #[test]
+    fn rows_that_fit() {
+        let limits = ResponseLimits {
+            max_bytes: Some(100),
+            ..Default::default()
+        };
+        assert_eq!(limits.rows_that_fit([30, 30, 30, 30], 0), 3);
+        assert_eq!(limits.rows_that_fit([30, 30, 30, 30], 40), 1);
+        // At least one row
+        assert_eq!(limits.rows_that_fit([500, 30], 0), 1);
+        assert_eq!(ResponseLimits::default().rows_that_fit([500, 30], 0), usize::MAX);
+    }
+}
@@ -0,0 +1,220 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Tools that check queries without running them, so that agents can fix malformed queries
+//! before searching large indices.
+
+use crate::servers::elasticsearch::base_tools::{EsBaseTools, EsqlQueryParams, EsqlQueryResponse};
+use crate::servers::elasticsearch::read_json;
+use elasticsearch::indices::IndicesValidateQueryParts;
+use rmcp::RoleServer;
+use rmcp::handler::server::tool::Parameters;
+use rmcp::model::{CallToolResult, Content};
+use rmcp::service::RequestContext;
+use rmcp_macros::{tool, tool_router};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct ValidateQueryParams {
+    /// Name of the Elasticsearch index to validate the query against
+    index: String,
+
+    /// Elasticsearch query DSL object, as for a search. Only its `query` property is validated.
+    query_body: Map<String, Value>,
+}
+
+#[tool_router(router = validation_tool_router, vis = "pub(super)")]
+impl EsBaseTools {
+    //---------------------------------------------------------------------------------------------
+    /// Tool: validate a query DSL object
+    #[tool(
+        description = "Check that an Elasticsearch query DSL object is valid for an index without running it, and explain how it is rewritten. Use it before searching large indices.",
+        annotations(title = "Validate ES query", read_only_hint = true)
+    )]
+    async fn validate_query(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(ValidateQueryParams { index, query_body }): Parameters<ValidateQueryParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let (body, ignored) = validate_body(query_body);
+        let response = es_client
+            .indices()
+            .validate_query(IndicesValidateQueryParts::Index(&[&index]))
+            .explain(true)
+            .body(body)
+            .send()
+            .await;
+        let response: ValidateQueryResponse = read_json(response).await?;
+
+        let mut contents = vec![Content::text(response.summary())];
+        if !ignored.is_empty() {
+            contents.push(Content::text(format!(
+                "Not validated: {}. Only the query is validated.",
+                ignored.join(", ")
+            )));
+        }
+        contents.push(Content::json(response.explanations)?);
+
+        Ok(CallToolResult::success(contents))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: validate an ES|QL query
+    #[tool(
+        description = "Check that an Elasticsearch ES|QL query is valid (syntax, index, column names and types) without returning results, by running it with `LIMIT 0`. Returns the columns the query would return.",
+        annotations(title = "Validate ES|QL query", read_only_hint = true)
+    )]
+    async fn esql_validate(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<EsqlQueryParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let mut request = params.into_request();
+        request.query = dry_run_query(&request.query);
+
+        // Invalid queries are returned as errors with their location
+        let response = es_client.esql().query().body(request).send().await;
+        let response: EsqlQueryResponse = read_json(response).await?;
+
+        Ok(CallToolResult::success(vec![
+            Content::text(format!(
+                "The ES|QL query is valid and returns {} columns:",
+                response.columns.len()
+            )),
+            Content::json(response.columns)?,
+        ]))
+    }
+}
+
+/// The validate API only accepts a query. Returns the request body, and the search properties
+/// that are ignored.
+fn validate_body(mut query_body: Map<String, Value>) -> (Value, Vec<String>) {
+    let ignored = query_body.keys().filter(|key| *key != "query").cloned().collect();
+    let body = match query_body.remove("query") {
+        Some(query) => json!({ "query": query }),
+        None => json!({}),
+    };
+    (body, ignored)
+}
+
+/// Add `LIMIT 0` to an ES|QL query, so that it's planned and verified but returns no rows. The
+/// limit is on a new line in case the query ends with a comment.
+fn dry_run_query(query: &str) -> String {
+    format!("{}\n| LIMIT 0", query.trim_end())
+}
+
+//-------------------------------------------------------------------------------------------------
+// Type definitions for ES responses
+
+#[derive(Serialize, Deserialize)]
+pub struct ValidateQueryResponse {
+    pub valid: bool,
+    /// Reason why the query is invalid, if `explain` isn't set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default)]
+    pub explanations: Vec<QueryExplanation>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct QueryExplanation {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<String>,
+    pub valid: bool,
+    /// The query rewritten as Lucene queries, if valid
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub explanation: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl ValidateQueryResponse {
+    fn summary(&self) -> String {
+        if self.valid {
+            return "The query is valid.".to_string();
+        }
+
+        let error = self
+            .error
+            .as_deref()
+            .or_else(|| self.explanations.iter().find_map(|e| e.error.as_deref()));
+        match error {
+            Some(error) => format!("The query is invalid: {error}"),
+            None => "The query is invalid.".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_request_body() {
+        let search_body = json!({
+            "query": { "match": { "message": "error" } },
+            "size": 10,
+            "aggs": { "hosts": { "terms": { "field": "host" } } }
+        });
+        let Value::Object(search_body) = search_body else {
+            unreachable!()
+        };
+
+        let (body, ignored) = validate_body(search_body);
+        assert_eq!(body, json!({ "query": { "match": { "message": "error" } } }));
+        assert_eq!(ignored, vec!["aggs", "size"]);
+    }
+
+    #[test]
+    fn validate_response_summary() -> anyhow::Result<()> {
+        let response: ValidateQueryResponse = serde_json::from_str(
+            r#"{
+                "_shards": { "total": 1, "successful": 1, "failed": 0 },
+                "valid": false,
+                "explanations": [{
+                    "index": "logs",
+                    "valid": false,
+                    "error": "org.elasticsearch.index.query.QueryShardException: failed to create query: For input string: \"abc\""
+                }]
+            }"#,
+        )?;
+        assert!(
+            response
+                .summary()
+                .starts_with("The query is invalid: org.elasticsearch")
+        );
+
+        let response: ValidateQueryResponse = serde_json::from_str(
+            r#"{ "valid": true, "explanations": [{ "index": "logs", "valid": true, "explanation": "message:error" }] }"#,
+        )?;
+        assert_eq!(response.summary(), "The query is valid.");
+        Ok(())
+    }
+
+    #[test]
+    fn esql_dry_run() {
+        assert_eq!(
+            dry_run_query("FROM logs | STATS count(*) BY host // count by host\n"),
+            "FROM logs | STATS count(*) BY host // count by host\n| LIMIT 0"
+        );
+    }
+}
@@ -0,0 +1,378 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Tools that modify data. They're only registered when the server is in `read_write` mode.
+
+use crate::servers::elasticsearch::base_tools::EsBaseTools;
+use crate::servers::elasticsearch::errors::EsError;
+use crate::servers::elasticsearch::{internal_error, read_json};
+use elasticsearch::http::StatusCode;
+use elasticsearch::http::request::JsonBody;
+use elasticsearch::http::response::Response;
+use elasticsearch::params::Refresh;
+use elasticsearch::{BulkParts, DeleteParts, IndexParts, UpdateParts};
+use rmcp::RoleServer;
+use rmcp::handler::server::tool::Parameters;
+use rmcp::model::{CallToolResult, Content};
+use rmcp::service::RequestContext;
+use rmcp_macros::{tool, tool_router};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+use std::collections::HashMap;
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct IndexDocumentParams {
+    /// Name of the Elasticsearch index or data stream
+    index: String,
+
+    /// Document id. If missing, an id is generated.
+    id: Option<String>,
+
+    /// The document to index
+    document: Map<String, Value>,
+
+    /// Wait for the change to be visible to searches before returning (optional)
+    refresh: Option<bool>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct UpdateDocumentParams {
+    /// Name of the Elasticsearch index
+    index: String,
+
+    /// Document id
+    id: String,
+
+    /// Partial document, whose fields are merged into the existing document
+    doc: Map<String, Value>,
+
+    /// Wait for the change to be visible to searches before returning (optional)
+    refresh: Option<bool>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct DeleteDocumentParams {
+    /// Name of the Elasticsearch index
+    index: String,
+
+    /// Document id
+    id: String,
+
+    /// Wait for the change to be visible to searches before returning (optional)
+    refresh: Option<bool>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct BulkParams {
+    /// Default index for operations that don't specify one (optional)
+    index: Option<String>,
+
+    /// Bulk request lines: each action (e.g. `{"index": {"_id": "1"}}`, `{"delete": {"_id": "2"}}`)
+    /// is followed by its document, except for `delete` actions
+    operations: Vec<Map<String, Value>>,
+
+    /// Wait for the changes to be visible to searches before returning (optional)
+    refresh: Option<bool>,
+}
+
+#[tool_router(router = write_tool_router, vis = "pub(super)")]
+impl EsBaseTools {
+    //---------------------------------------------------------------------------------------------
+    /// Tool: index a document
+    #[tool(
+        description = "Index a document in an Elasticsearch index, creating or replacing it.",
+        annotations(title = "Index ES document", read_only_hint = false, destructive_hint = true)
+    )]
+    async fn index_document(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(IndexDocumentParams {
+            index,
+            id,
+            document,
+            refresh,
+        }): Parameters<IndexDocumentParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let parts = match &id {
+            Some(id) => IndexParts::IndexId(&index, id),
+            None => IndexParts::Index(&index),
+        };
+        let response = es_client
+            .index(parts)
+            .refresh(refresh_param(refresh))
+            .body(document)
+            .send()
+            .await;
+
+        let response: WriteResponse = read_json(response).await?;
+
+        Ok(CallToolResult::success(vec![
+            Content::text(format!("Document {} {}", response.id, response.result)),
+            Content::json(response)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: update a document
+    #[tool(
+        description = "Update a document by id with a partial document.",
+        annotations(title = "Update ES document", read_only_hint = false, destructive_hint = true)
+    )]
+    async fn update_document(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(UpdateDocumentParams {
+            index,
+            id,
+            doc,
+            refresh,
+        }): Parameters<UpdateDocumentParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let response = es_client
+            .update(UpdateParts::IndexId(&index, &id))
+            .refresh(refresh_param(refresh))
+            .body(json!({ "doc": doc }))
+            .send()
+            .await;
+
+        let response: WriteResponse = read_json(response).await?;
+
+        Ok(CallToolResult::success(vec![
+            Content::text(format!("Document {} {}", response.id, response.result)),
+            Content::json(response)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: delete a document
+    #[tool(
+        description = "Delete a document by id. A missing document is reported with a `not_found` result.",
+        annotations(title = "Delete ES document", read_only_hint = false, destructive_hint = true)
+    )]
+    async fn delete_document(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(DeleteDocumentParams { index, id, refresh }): Parameters<DeleteDocumentParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let response = es_client
+            .delete(DeleteParts::IndexId(&index, &id))
+            .refresh(refresh_param(refresh))
+            .send()
+            .await;
+
+        let response = read_delete_response(response).await?;
+
+        Ok(CallToolResult::success(vec![
+            Content::text(format!("Document {} {}", response.id, response.result)),
+            Content::json(response)?,
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: bulk operations
+    #[tool(
+        description = "Perform several index, create, update or delete operations in a single Elasticsearch bulk request.",
+        annotations(title = "ES bulk request", read_only_hint = false, destructive_hint = true)
+    )]
+    async fn bulk(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(BulkParams {
+            index,
+            operations,
+            refresh,
+        }): Parameters<BulkParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let parts = match &index {
+            Some(index) => BulkParts::Index(index),
+            None => BulkParts::None,
+        };
+        let body = operations
+            .into_iter()
+            .map(|op| JsonBody::new(Value::Object(op)))
+            .collect::<Vec<_>>();
+
+        let response = es_client
+            .bulk(parts)
+            .refresh(refresh_param(refresh))
+            .body(body)
+            .send()
+            .await;
+
+        let response: BulkResponse = read_json(response).await?;
+        let count = response.items.len();
+        let failures = response.failures();
+
+        Ok(CallToolResult::success(vec![
+            Content::text(format!("Bulk request: {count} operations, {} failed", failures.len())),
+            Content::json(failures)?,
+        ]))
+    }
+}
+
+/// A missing document is a 404 response with a `not_found` result, which isn't an error. Other
+/// 404 responses, like a missing index, are errors.
+async fn read_delete_response(response: Result<Response, elasticsearch::Error>) -> Result<WriteResponse, rmcp::Error> {
+    match response {
+        Ok(response) if response.status_code() == StatusCode::NOT_FOUND => {
+            let body = response.text().await.map_err(internal_error)?;
+            parse_delete_not_found(&body)
+        }
+        response => read_json(response).await,
+    }
+}
+
+fn parse_delete_not_found(body: &str) -> Result<WriteResponse, rmcp::Error> {
+    serde_json::from_str(body).map_err(|_| EsError::from_response(StatusCode::NOT_FOUND, body).into_rmcp())
+}
+
+fn refresh_param(refresh: Option<bool>) -> Refresh {
+    if refresh.unwrap_or(false) {
+        Refresh::WaitFor
+    } else {
+        Refresh::False
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Type definitions for ES responses
+
+/// Response of the index, update and delete APIs
+#[derive(Serialize, Deserialize)]
+pub struct WriteResponse {
+    #[serde(rename = "_index")]
+    pub index: String,
+    #[serde(rename = "_id")]
+    pub id: String,
+    #[serde(rename = "_version", default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u64>,
+    /// `created`, `updated`, `deleted`, `noop`, or `not_found`
+    pub result: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BulkResponse {
+    pub errors: bool,
+    /// Items, as action name and result
+    pub items: Vec<HashMap<String, BulkItem>>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BulkItem {
+    #[serde(rename = "_index")]
+    pub index: String,
+    #[serde(rename = "_id", default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub status: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<Value>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BulkFailure {
+    /// Index of the operation in the request, starting at 0. Document lines aren't counted, so
+    /// this is not the line number in the request body.
+    pub operation: usize,
+    pub action: String,
+    #[serde(flatten)]
+    pub item: BulkItem,
+}
+
+impl BulkResponse {
+    /// Operations that failed
+    pub fn failures(self) -> Vec<BulkFailure> {
+        if !self.errors {
+            return Vec::new();
+        }
+
+        self.items
+            .into_iter()
+            .enumerate()
+            .flat_map(|(operation, item)| {
+                item.into_iter()
+                    .filter(|(_, item)| item.error.is_some())
+                    .map(move |(action, item)| BulkFailure {
+                        operation,
+                        action,
+                        item,
+                    })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bulk_failures() -> anyhow::Result<()> {
+        let response: BulkResponse = serde_json::from_str(
+            r#"{
+                "took": 3,
+                "errors": true,
+                "items": [
+                    { "index": { "_index": "test", "_id": "1", "status": 201, "result": "created" } },
+                    { "delete": { "_index": "test", "_id": "2", "status": 404, "result": "not_found" } },
+                    { "update": { "_index": "test", "_id": "3", "status": 404,
+                        "error": { "type": "document_missing_exception", "reason": "[3]: document missing" } } }
+                ]
+            }"#,
+        )?;
+
+        let failures = serde_json::to_value(response.failures())?;
+        assert_eq!(
+            failures,
+            json!([{
+                "operation": 2,
+                "action": "update",
+                "_index": "test",
+                "_id": "3",
+                "status": 404,
+                "error": { "type": "document_missing_exception", "reason": "[3]: document missing" }
+            }])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn delete_not_found() -> anyhow::Result<()> {
+        let response = parse_delete_not_found(
+            r#"{ "_index": "test", "_id": "1", "_version": 1, "result": "not_found", "_shards": {} }"#,
+        )?;
+        assert_eq!(response.result, "not_found");
+
+        let error = parse_delete_not_found(
+            r#"{ "error": { "type": "index_not_found_exception", "reason": "no such index [test]", "index": "test" }, "status": 404 }"#,
+        )
+        .err()
+        .and_then(|err| EsError::from_rmcp(&err));
+        assert_eq!(
+            error.map(|err| err.type_),
+            Some("index_not_found_exception".to_string())
+        );
+        Ok(())
+    }
+}
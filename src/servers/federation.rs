@@ -0,0 +1,208 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Federation of downstream MCP servers: their tools are exposed by this server, prefixed with
+//! the server name, and calls are forwarded to them.
+//!
+//! Tool lists are fetched once at startup, and changes in downstream servers are not tracked.
+
+use crate::cli::{Http, McpServer, Stdio};
+use crate::servers::IncludeExclude;
+use crate::servers::elasticsearch::Mode;
+use anyhow::Context;
+use futures::FutureExt;
+use http::{HeaderMap, HeaderName, HeaderValue};
+use rmcp::handler::server::router::tool::{ToolRoute, ToolRouter};
+use rmcp::handler::server::tool::ToolCallContext;
+use rmcp::model::{CallToolRequestParam, Tool};
+use rmcp::service::RunningService;
+use rmcp::transport::sse_client::SseClientConfig;
+use rmcp::transport::streamable_http_client::StreamableHttpClientTransportConfig;
+use rmcp::transport::{SseClientTransport, StreamableHttpClientTransport, TokioChildProcess};
+use rmcp::{RoleClient, ServiceError, ServiceExt};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Separator between the server name and the tool name
+const NAMESPACE_SEPARATOR: &str = "__";
+
+type McpClient = RunningService<RoleClient, ()>;
+
+/// Connect to the downstream MCP servers and create a router that forwards calls to their tools.
+///
+/// Servers that can't be reached are skipped with a warning, so that they don't prevent this
+/// server from starting.
+pub async fn tool_router<S: Send + Sync + 'static>(
+    servers: HashMap<String, McpServer>,
+    mode: Mode,
+    incl_excl: Option<&IncludeExclude>,
+) -> ToolRouter<S> {
+    let mut router = ToolRouter::new();
+
+    for (server_name, server) in servers.into_iter() {
+        let (client, tools) = match connect_and_list(&server_name, server).await {
+            Ok(result) => result,
+            Err(err) => {
+                tracing::warn!("Skipping MCP server '{server_name}': {err:#}");
+                continue;
+            }
+        };
+
+        let tools = filter_tools(&server_name, tools, mode, incl_excl);
+        tracing::info!("Adding {} tools from MCP server '{server_name}'", tools.len());
+
+        // Keep the client alive as long as the router: dropping it closes the connection.
+        let client = Arc::new(client);
+        for tool in tools {
+            router.add_route(forward_route(&server_name, tool, client.clone()));
+        }
+    }
+
+    router
+}
+
+async fn connect_and_list(server_name: &str, server: McpServer) -> anyhow::Result<(McpClient, Vec<Tool>)> {
+    let client = connect(server)
+        .await
+        .with_context(|| format!("Failed to connect to MCP server '{server_name}'"))?;
+
+    let tools = client
+        .list_all_tools()
+        .await
+        .with_context(|| format!("Failed to list tools of MCP server '{server_name}'"))?;
+
+    Ok((client, tools))
+}
+
+/// Keep the tools of a downstream server that are included by `incl_excl`, using their prefixed
+/// name. In read-only `mode`, only tools annotated as read-only are kept: this server can't check
+/// what the other tools do.
+fn filter_tools(server_name: &str, tools: Vec<Tool>, mode: Mode, incl_excl: Option<&IncludeExclude>) -> Vec<Tool> {
+    tools
+        .into_iter()
+        .filter(|tool| {
+            let read_only = tool.annotations.as_ref().and_then(|a| a.read_only_hint) == Some(true);
+            mode == Mode::ReadWrite || read_only
+        })
+        .filter(|tool| {
+            incl_excl.is_none_or(|incl_excl| incl_excl.is_included(&namespaced_name(server_name, &tool.name)))
+        })
+        .collect()
+}
+
+async fn connect(server: McpServer) -> anyhow::Result<McpClient> {
+    let client = match server {
+        McpServer::Stdio(Stdio { command, args, env }) => {
+            let mut cmd = tokio::process::Command::new(command);
+            cmd.args(args).envs(env);
+            ().serve(TokioChildProcess::new(cmd)?).await?
+        }
+        McpServer::Sse(Http { url, headers }) => {
+            let transport = SseClientTransport::start_with_client(
+                http_client(headers)?,
+                SseClientConfig {
+                    sse_endpoint: url.into(),
+                    ..Default::default()
+                },
+            )
+            .await?;
+            ().serve(transport).await?
+        }
+        McpServer::StreamableHttp(Http { url, headers }) => {
+            let transport = StreamableHttpClientTransport::with_client(
+                http_client(headers)?,
+                StreamableHttpClientTransportConfig::with_uri(url),
+            );
+            ().serve(transport).await?
+        }
+    };
+
+    Ok(client)
+}
+
+/// Create an http client that sends `headers` with every request
+fn http_client(headers: HashMap<String, String>) -> anyhow::Result<reqwest::Client> {
+    let mut header_map = HeaderMap::new();
+    for (name, value) in headers {
+        header_map.insert(HeaderName::try_from(name)?, HeaderValue::try_from(value)?);
+    }
+
+    Ok(reqwest::Client::builder().default_headers(header_map).build()?)
+}
+
+/// Name of a downstream tool in this server
+fn namespaced_name(server_name: &str, tool_name: &str) -> String {
+    format!("{server_name}{NAMESPACE_SEPARATOR}{tool_name}")
+}
+
+fn forward_route<S: Send + Sync + 'static>(server_name: &str, tool: Tool, client: Arc<McpClient>) -> ToolRoute<S> {
+    let tool_name = tool.name.clone();
+    let attr = Tool {
+        name: namespaced_name(server_name, &tool.name).into(),
+        ..tool
+    };
+
+    ToolRoute::new_dyn(attr, move |ctx: ToolCallContext<'_, S>| {
+        let client = client.clone();
+        let request = CallToolRequestParam {
+            name: tool_name.clone(),
+            arguments: ctx.arguments,
+        };
+        async move {
+            client.call_tool(request).await.map_err(|e| match e {
+                ServiceError::McpError(e) => e,
+                e => rmcp::Error::internal_error(e.to_string(), None),
+            })
+        }
+        .boxed()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::model::{JsonObject, ToolAnnotations};
+
+    #[test]
+    fn tool_namespace() {
+        assert_eq!(namespaced_name("helpers", "get_time"), "helpers__get_time");
+    }
+
+    #[test]
+    fn downstream_tools_filter() {
+        let tools = vec![
+            Tool::new("get_time", "Get the time", JsonObject::new()).annotate(ToolAnnotations::new().read_only(true)),
+            Tool::new("send_mail", "Send an email", JsonObject::new()),
+        ];
+        let names = |tools: Vec<Tool>| tools.into_iter().map(|t| t.name.to_string()).collect::<Vec<_>>();
+
+        let filtered = filter_tools("helpers", tools.clone(), Mode::ReadOnly, None);
+        assert_eq!(names(filtered), vec!["get_time"]);
+
+        let filtered = filter_tools("helpers", tools.clone(), Mode::ReadWrite, None);
+        assert_eq!(names(filtered), vec!["get_time", "send_mail"]);
+
+        let exclude = IncludeExclude::Exclude(vec!["helpers__get_time".to_string()]);
+        let filtered = filter_tools("helpers", tools.clone(), Mode::ReadWrite, Some(&exclude));
+        assert_eq!(names(filtered), vec!["send_mail"]);
+
+        // Unprefixed names don't match
+        let include = IncludeExclude::Include(vec!["send_mail".to_string()]);
+        let filtered = filter_tools("helpers", tools, Mode::ReadWrite, Some(&include));
+        assert!(filtered.is_empty());
+    }
+}
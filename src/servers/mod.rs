@@ -15,12 +15,14 @@
 // specific language governing permissions and limitations
 // under the License.
 
+use rmcp::handler::server::router::tool::ToolRouter;
 use serde::{Deserialize, Serialize};
 
 pub mod elasticsearch;
+pub mod federation;
 
 /// Inclusion or exclusion list.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum IncludeExclude {
     Include(Vec<String>),
@@ -39,4 +41,74 @@ impl IncludeExclude {
     pub fn filter(&self, tools: &mut Vec<rmcp::model::Tool>) {
         tools.retain(|t| self.is_included(&t.name))
     }
+
+    /// Remove excluded tools from a router, so that they're neither listed nor callable.
+    pub fn filter_router<S>(&self, router: &mut ToolRouter<S>) {
+        router.map.retain(|name, _| self.is_included(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::FutureExt;
+    use rmcp::handler::server::router::tool::ToolRoute;
+    use rmcp::model::{CallToolRequestParam, CallToolResult, JsonObject, Tool};
+    use rmcp::{ServerHandler, ServiceExt};
+    use rmcp_macros::tool_handler;
+
+    #[derive(Clone)]
+    struct TestServer {
+        tool_router: ToolRouter<Self>,
+    }
+
+    #[tool_handler]
+    impl ServerHandler for TestServer {}
+
+    fn route(name: &'static str) -> ToolRoute<TestServer> {
+        ToolRoute::new_dyn(Tool::new(name, "Test tool", JsonObject::new()), |_| {
+            async { Ok(CallToolResult::success(Vec::new())) }.boxed()
+        })
+    }
+
+    #[test]
+    fn include_exclude() {
+        let include = IncludeExclude::Include(vec!["esql".to_string()]);
+        assert!(include.is_included("esql"));
+        assert!(!include.is_included("es_search"));
+
+        let exclude = IncludeExclude::Exclude(vec!["esql".to_string()]);
+        assert!(!exclude.is_included("esql"));
+        assert!(exclude.is_included("es_search"));
+    }
+
+    #[tokio::test]
+    async fn filter_router() -> anyhow::Result<()> {
+        let mut tool_router = ToolRouter::new().with_route(route("esql")).with_route(route("search"));
+        IncludeExclude::Exclude(vec!["esql".to_string()]).filter_router(&mut tool_router);
+
+        let (server_io, client_io) = tokio::io::duplex(4096);
+        tokio::spawn(async move {
+            let server = TestServer { tool_router }.serve(server_io).await?;
+            server.waiting().await?;
+            anyhow::Ok(())
+        });
+        let client = ().serve(client_io).await?;
+
+        let tools = client.list_tools(None).await?.tools;
+        assert_eq!(
+            tools.iter().map(|t| t.name.as_ref()).collect::<Vec<_>>(),
+            vec!["search"]
+        );
+
+        let call = |name: &'static str| CallToolRequestParam {
+            name: name.into(),
+            arguments: None,
+        };
+        assert!(client.call_tool(call("search")).await.is_ok());
+        assert!(client.call_tool(call("esql")).await.is_err());
+
+        client.cancel().await?;
+        Ok(())
+    }
 }